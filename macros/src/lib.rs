@@ -73,7 +73,7 @@ fn message_decode(input: TokenStream) -> TokenStream {
 				}
 
 				let extra = if custom_decode {
-					quote! { s.decode(&mut r) }
+					quote! { s.decode(r)? }
 				} else { quote! {} };
 
 				cases.push(quote! { #code => {
@@ -96,14 +96,31 @@ fn message_decode(input: TokenStream) -> TokenStream {
 	}
 
 	TokenStream::from(quote! {
-		impl<'a> From<Vec<u8>> for Message  {
-			fn from(buf: Vec<u8>) -> Self {
-				let mut r = Reader::new(&buf);
-
-				match r.read_byte() {
+		impl Message {
+			fn decode(code: u8, r: &mut Reader) -> Result<Self, ReadError> {
+				Ok(match code {
 					#(#cases),*,
-					code => Self::Custom(code, buf.clone()),
-				}
+					code => Self::Custom(code, r.read_remaining()?),
+				})
+			}
+		}
+
+		impl TryFrom<Vec<u8>> for Message {
+			type Error = MessageDecodeError;
+
+			fn try_from(buf: Vec<u8>) -> Result<Self, Self::Error> {
+				let mut r = Reader::new(&buf);
+				let code = r.read_byte().map_err(|error| MessageDecodeError::Malformed {
+					code: 0,
+					offset: 0,
+					error,
+				})?;
+
+				Self::decode(code, &mut r).map_err(|error| MessageDecodeError::Malformed {
+					code,
+					offset: r.cur,
+					error,
+				})
 			}
 		}
 	})
@@ -111,22 +128,22 @@ fn message_decode(input: TokenStream) -> TokenStream {
 
 fn type_to_reader_method(s: &str) -> TokenStream2 {
 	match s {
-		"bool" => quote! { r.read_bool() },
-		"u8" => quote! { r.read_byte() },
-		"i8" => quote! { r.read_i8() },
-		"u16" => quote! { r.read_u16() },
-		"i16" => quote! { r.read_i16() },
-		"u32" => quote! { r.read_u32() },
-		"i32" => quote! { r.read_i32() },
-		"u64" => quote! { r.read_u64() },
-		"i64" => quote! { r.read_i64() },
-		"f32" => quote! { r.read_f32() },
-		"f64" => quote! { r.read_f64() },
-		"String" => quote! { r.read_string() },
-		"Text" => quote! { r.read_text() },
-		"RGB" => quote! { r.read_rgb() },
-		"Vector2" => quote! { r.read_vector2() },
-		"Vec" => quote! { r.buf[r.cur..].to_vec() },
+		"bool" => quote! { r.read_bool()? },
+		"u8" => quote! { r.read_byte()? },
+		"i8" => quote! { r.read_i8()? },
+		"u16" => quote! { r.read_u16()? },
+		"i16" => quote! { r.read_i16()? },
+		"u32" => quote! { r.read_u32()? },
+		"i32" => quote! { r.read_i32()? },
+		"u64" => quote! { r.read_u64()? },
+		"i64" => quote! { r.read_i64()? },
+		"f32" => quote! { r.read_f32()? },
+		"f64" => quote! { r.read_f64()? },
+		"String" => quote! { r.read_string()? },
+		"Text" => quote! { r.read_text()? },
+		"RGB" => quote! { r.read_rgb()? },
+		"Vector2" => quote! { r.read_vector2()? },
+		"Vec" => quote! { r.read_remaining()? },
 		"Option" => quote!{ None },
 		ty => quote! { compile_error!(format!("Unsupported type: {}", #ty)) },
 	}
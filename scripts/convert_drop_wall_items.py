@@ -30,7 +30,7 @@ for m in nums:
 			next(nums)
 			continue
 
-		rust += f'\n{num}..={nn()} => self.id + {nn()-nn()},'
+		rust += f'\n{num}..={nn()} => self.wall as i16 + {nn()-nn()},'
 	elif src[j+1] == '|':
 		rust += f'\n{num} | {nn()} => {nn()},'
 	elif src[j] in (':', ')'):
@@ -1,93 +1,118 @@
-use crate::binary::types::{Text, Vector2, RGB};
+use crate::binary::types::{ReadError, Text, Vector2, RGB};
 
 pub struct Reader<'a> {
 	pub buf: &'a [u8],
 	pub cur: usize,
 }
 
+type R<T> = Result<T, ReadError>;
+
+// Vanilla never nests substitutions this deep, so anything past it is garbage
+const MAX_TEXT_DEPTH: usize = 16;
+
 #[allow(dead_code)]
 impl<'a> Reader<'a> {
 	pub fn new(buf: &'a [u8]) -> Self {
 		Self { buf, cur: 0 }
 	}
 
-	pub fn read_bytes(&mut self, amount: usize) -> &[u8] {
+	pub fn read_bytes(&mut self, amount: usize) -> R<&[u8]> {
+		if self.cur + amount > self.buf.len() {
+			return Err(ReadError::UnexpectedEOI);
+		}
+
 		self.cur += amount;
-		&self.buf[(self.cur - amount)..self.cur]
+		Ok(&self.buf[(self.cur - amount)..self.cur])
+	}
+
+	pub fn read_remaining(&mut self) -> R<Vec<u8>> {
+		Ok(self.read_bytes(self.buf.len() - self.cur)?.to_vec())
 	}
 
-	pub fn read_byte(&mut self) -> u8 {
-		self.read_bytes(1)[0]
+	pub fn read_byte(&mut self) -> R<u8> {
+		Ok(self.read_bytes(1)?[0])
 	}
 
-	pub fn read_bool(&mut self) -> bool {
-		self.read_byte() != 0
+	pub fn read_bool(&mut self) -> R<bool> {
+		Ok(self.read_byte()? != 0)
 	}
 
-	pub fn read_i8(&mut self) -> i8 {
-		self.read_byte() as i8
+	pub fn read_i8(&mut self) -> R<i8> {
+		Ok(self.read_byte()? as i8)
 	}
 
-	pub fn read_u16(&mut self) -> u16 {
-		u16::from_le_bytes(self.read_bytes(2).try_into().unwrap_or([0; 2]))
+	pub fn read_u16(&mut self) -> R<u16> {
+		Ok(u16::from_le_bytes(self.read_bytes(2)?.try_into().unwrap()))
 	}
 
-	pub fn read_i16(&mut self) -> i16 {
-		i16::from_le_bytes(self.read_bytes(2).try_into().unwrap_or([0; 2]))
+	pub fn read_i16(&mut self) -> R<i16> {
+		Ok(i16::from_le_bytes(self.read_bytes(2)?.try_into().unwrap()))
 	}
 
-	pub fn read_u32(&mut self) -> u32 {
-		u32::from_le_bytes(self.read_bytes(4).try_into().unwrap_or([0; 4]))
+	pub fn read_u32(&mut self) -> R<u32> {
+		Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
 	}
 
-	pub fn read_i32(&mut self) -> i32 {
-		i32::from_le_bytes(self.read_bytes(4).try_into().unwrap_or([0; 4]))
+	pub fn read_i32(&mut self) -> R<i32> {
+		Ok(i32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
 	}
 
-	pub fn read_u64(&mut self) -> u64 {
-		u64::from_le_bytes(self.read_bytes(8).try_into().unwrap_or([0; 8]))
+	pub fn read_u64(&mut self) -> R<u64> {
+		Ok(u64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
 	}
 
-	pub fn read_i64(&mut self) -> i64 {
-		i64::from_le_bytes(self.read_bytes(8).try_into().unwrap_or([0; 8]))
+	pub fn read_i64(&mut self) -> R<i64> {
+		Ok(i64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
 	}
 
-	pub fn read_f32(&mut self) -> f32 {
-		f32::from_le_bytes(self.read_bytes(4).try_into().unwrap_or([0; 4]))
+	pub fn read_f32(&mut self) -> R<f32> {
+		Ok(f32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
 	}
 
-	pub fn read_f64(&mut self) -> f64 {
-		f64::from_le_bytes(self.read_bytes(8).try_into().unwrap_or([0; 8]))
+	pub fn read_f64(&mut self) -> R<f64> {
+		Ok(f64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
 	}
 
-	pub fn read_length(&mut self) -> usize {
-		let mut length = self.read_byte() as usize;
+	pub fn read_length(&mut self) -> R<usize> {
+		let mut length = self.read_byte()? as usize;
 		let mut shift = 7;
 		while length & (1 << shift) != 0 {
+			// 7 bit encoded ints are at most 5 bytes long
+			if shift > 28 {
+				return Err(ReadError::InvalidLength);
+			}
 			length &= !(1 << shift);
-			length |= (self.read_byte() as usize) << shift;
+			length |= (self.read_byte()? as usize) << shift;
 			shift += 7;
 		}
 
-		length
+		Ok(length)
+	}
+
+	pub fn read_string(&mut self) -> R<String> {
+		let length = self.read_length()?;
+		Ok(std::str::from_utf8(self.read_bytes(length)?)
+			.map_err(ReadError::InvalidString)?
+			.to_owned())
 	}
 
-	pub fn read_string(&mut self) -> String {
-		let length = self.read_length();
-		std::str::from_utf8(self.read_bytes(length))
-			.unwrap_or("")
-			.to_string()
+	pub fn read_text(&mut self) -> R<Text> {
+		self.read_text_nested(0)
 	}
 
-	pub fn read_text(&mut self) -> Text {
-		let kind = self.read_byte();
-		match kind {
-			0 => Text::Literal(self.read_string()),
+	fn read_text_nested(&mut self, depth: usize) -> R<Text> {
+		if depth > MAX_TEXT_DEPTH {
+			return Err(ReadError::TextTooDeep);
+		}
+
+		let kind = self.read_byte()?;
+		Ok(match kind {
+			0 => Text::Literal(self.read_string()?),
 			1 | 2 => {
-				let form = self.read_string();
-				let mut subs = Vec::with_capacity(self.read_byte() as usize);
+				let form = self.read_string()?;
+				let mut subs = Vec::with_capacity(self.read_byte()? as usize);
 				for _ in 0..subs.capacity() {
-					subs.push(self.read_text())
+					subs.push(self.read_text_nested(depth + 1)?)
 				}
 				if kind == 1 {
 					Text::Formattable(form, subs)
@@ -96,14 +121,14 @@ impl<'a> Reader<'a> {
 				}
 			}
 			_ => Text::Invalid,
-		}
+		})
 	}
 
-	pub fn read_rgb(&mut self) -> RGB {
-		RGB(self.read_byte(), self.read_byte(), self.read_byte())
+	pub fn read_rgb(&mut self) -> R<RGB> {
+		Ok(RGB(self.read_byte()?, self.read_byte()?, self.read_byte()?))
 	}
 
-	pub fn read_vector2(&mut self) -> Vector2 {
-		Vector2(self.read_f32(), self.read_f32())
+	pub fn read_vector2(&mut self) -> R<Vector2> {
+		Ok(Vector2(self.read_f32()?, self.read_f32()?))
 	}
 }
@@ -1,3 +1,5 @@
+use std::{error::Error, fmt, str::Utf8Error};
+
 #[derive(Debug, Clone, Default)]
 pub struct RGB(pub u8, pub u8, pub u8);
 
@@ -23,3 +25,24 @@ impl Text {
 		}
 	}
 }
+
+#[derive(Debug)]
+pub enum ReadError {
+	UnexpectedEOI,
+	InvalidLength,
+	TextTooDeep,
+	InvalidString(Utf8Error),
+}
+
+impl fmt::Display for ReadError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::UnexpectedEOI => write!(f, "Expected more data but reached end of input"),
+			Self::InvalidLength => write!(f, "Could not parse length prefix"),
+			Self::TextTooDeep => write!(f, "Text is nested too deeply"),
+			Self::InvalidString(err) => write!(f, "Could not parse string, got {}", err),
+		}
+	}
+}
+
+impl Error for ReadError {}
@@ -0,0 +1,262 @@
+use directories::UserDirs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{error::Error, fmt, fs, io};
+
+use crate::log::LogLevel;
+use crate::network::outbox::{LagPolicy, DEFAULT_QUEUE_SIZE};
+use crate::network::save::{DEFAULT_AUTOSAVE_INTERVAL, DEFAULT_BACKUP_COUNT};
+use crate::network::server::MAX_CLIENTS;
+
+pub const DEFAULT_CONFIG_PATH: &str = "trust.conf";
+
+pub const USAGE: &str = "\
+Usage: trust [options]
+
+Options:
+  --config <path>         Config file to read, trust.conf if it exists
+  --world <path|name>     World file, or the name of a world in the Terraria Worlds folder
+  --bind <addr,...>       Addresses to listen on (default 0.0.0.0:7777)
+  --password <password>   Password players need to join
+  --max-players <n>       Maximum number of connected players (default 16)
+  --motd <text>           Message shown to players when they join
+  --autosave <minutes>    Minutes between autosaves, 0 to disable (default 10)
+  --backups <n>           Number of .bak.N copies kept when saving (default 3)
+  --log-level <level>     error, warn, info or debug (default info)
+  --queue-size <n>        Messages queued per player before the lag policy applies (default 256)
+  --lag-policy <policy>   drop, resync or kick players that fall behind (default drop)
+  --help                  Show this message
+
+The config file takes the same options as `key = value` lines, with `-` in names
+written as `_` (e.g. `max_players = 8`). Lines starting with # are ignored.
+Command line options override the config file.";
+
+#[derive(Debug, Clone)]
+pub struct Config {
+	pub world: Option<String>,
+	pub bind: Vec<String>,
+	pub password: String,
+	pub max_players: usize,
+	pub motd: String,
+	pub autosave_interval: Duration,
+	pub backup_count: usize,
+	pub log_level: LogLevel,
+	pub queue_size: usize,
+	pub lag_policy: LagPolicy,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+	IO(PathBuf, io::Error),
+	InvalidLine(usize, String),
+	UnknownOption(String),
+	MissingValue(String),
+	InvalidValue(String, String),
+	NoWorld,
+	NoWorldsFolder,
+	WorldNotFound(String, Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::IO(path, err) => write!(f, "Could not read {}: {}", path.display(), err),
+			Self::InvalidLine(line, content) => {
+				write!(f, "Expected `key = value` on line {}, got `{}`", line, content)
+			}
+			Self::UnknownOption(name) => write!(f, "Unknown option `{}`", name),
+			Self::MissingValue(name) => write!(f, "Option `{}` needs a value", name),
+			Self::InvalidValue(name, value) => {
+				write!(f, "Invalid value `{}` for option `{}`", value, name)
+			}
+			Self::NoWorld => write!(f, "No world given, use --world <path|name>"),
+			Self::NoWorldsFolder => write!(f, "Could not find the Terraria Worlds folder"),
+			Self::WorldNotFound(name, available) => write!(
+				f,
+				"No world named `{}`, available worlds: {}",
+				name,
+				available.join(", ")
+			),
+		}
+	}
+}
+
+impl Error for ConfigError {}
+
+impl Default for Config {
+	fn default() -> Self {
+		Config {
+			world: None,
+			bind: vec!["0.0.0.0:7777".to_owned()],
+			password: String::new(),
+			max_players: 16,
+			motd: String::new(),
+			autosave_interval: DEFAULT_AUTOSAVE_INTERVAL,
+			backup_count: DEFAULT_BACKUP_COUNT,
+			log_level: LogLevel::Info,
+			queue_size: DEFAULT_QUEUE_SIZE,
+			lag_policy: LagPolicy::Drop,
+		}
+	}
+}
+
+impl Config {
+	// Reads the config file, then applies the command line on top of it
+	pub fn from_args(args: &[String]) -> Result<Self, ConfigError> {
+		let mut flags = vec![];
+		let mut config_path = None;
+
+		let mut iter = args.iter();
+		while let Some(arg) = iter.next() {
+			let Some(name) = arg.strip_prefix("--") else {
+				return Err(ConfigError::UnknownOption(arg.clone()));
+			};
+			let Some(value) = iter.next() else {
+				return Err(ConfigError::MissingValue(name.to_owned()));
+			};
+
+			if name == "config" {
+				config_path = Some(PathBuf::from(value));
+			} else {
+				flags.push((name.replace('-', "_"), value.clone()));
+			}
+		}
+
+		let mut config = match config_path {
+			Some(path) => Self::from_file(&path)?,
+			None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+				Self::from_file(Path::new(DEFAULT_CONFIG_PATH))?
+			}
+			None => Self::default(),
+		};
+
+		for (name, value) in flags {
+			config.set(&name, &value)?;
+		}
+
+		Ok(config)
+	}
+
+	pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+		let content = fs::read_to_string(path).map_err(|e| ConfigError::IO(path.to_owned(), e))?;
+		let mut config = Self::default();
+
+		for (i, line) in content.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+
+			let Some((name, value)) = line.split_once('=') else {
+				return Err(ConfigError::InvalidLine(i + 1, line.to_owned()));
+			};
+			config.set(name.trim(), value.trim())?;
+		}
+
+		Ok(config)
+	}
+
+	fn set(&mut self, name: &str, value: &str) -> Result<(), ConfigError> {
+		let invalid = || ConfigError::InvalidValue(name.to_owned(), value.to_owned());
+
+		match name {
+			"world" => self.world = Some(value.to_owned()),
+			"bind" => {
+				self.bind = value
+					.split(',')
+					.map(str::trim)
+					.filter(|a| !a.is_empty())
+					.map(str::to_owned)
+					.collect();
+				if self.bind.is_empty() {
+					return Err(invalid());
+				}
+			}
+			"password" => self.password = value.to_owned(),
+			"max_players" => {
+				// The last slot is reserved, client id 255 means "everyone" in several messages
+				self.max_players = value.parse().map_err(|_| invalid())?;
+				if !(1..MAX_CLIENTS).contains(&self.max_players) {
+					return Err(invalid());
+				}
+			}
+			"motd" => self.motd = value.to_owned(),
+			"autosave" => {
+				let minutes: u64 = value.parse().map_err(|_| invalid())?;
+				self.autosave_interval = Duration::from_secs(minutes * 60);
+			}
+			"backups" => self.backup_count = value.parse().map_err(|_| invalid())?,
+			"log_level" => self.log_level = value.parse().map_err(|_| invalid())?,
+			"queue_size" => {
+				self.queue_size = value.parse().map_err(|_| invalid())?;
+				if self.queue_size == 0 {
+					return Err(invalid());
+				}
+			}
+			"lag_policy" => self.lag_policy = value.parse().map_err(|_| invalid())?,
+			_ => return Err(ConfigError::UnknownOption(name.to_owned())),
+		}
+
+		Ok(())
+	}
+
+	// Anything that looks like a path is used as is, otherwise the name is looked up in the Worlds folder
+	pub fn world_path(&self) -> Result<PathBuf, ConfigError> {
+		let Some(world) = &self.world else {
+			return Err(ConfigError::NoWorld);
+		};
+
+		let path = Path::new(world);
+		if path.exists() || path.extension().is_some() || path.components().count() > 1 {
+			return Ok(path.to_owned());
+		}
+
+		let worlds = find_worlds()?;
+		let wanted = normalize_world_name(world);
+		worlds
+			.iter()
+			.find(|p| world_name(p).is_some_and(|n| normalize_world_name(&n) == wanted))
+			.cloned()
+			.ok_or_else(|| {
+				ConfigError::WorldNotFound(
+					world.clone(),
+					worlds.iter().filter_map(|p| world_name(p)).collect(),
+				)
+			})
+	}
+}
+
+pub fn worlds_dir() -> Option<PathBuf> {
+	let user_dirs = UserDirs::new()?;
+
+	// Terraria keeps its saves in Application Support on macOS and in Documents everywhere else
+	if cfg!(target_os = "macos") {
+		Some(user_dirs.home_dir().join("Library").join("Application Support").join("Terraria").join("Worlds"))
+	} else if cfg!(target_os = "windows") {
+		Some(user_dirs.document_dir()?.join("My Games").join("Terraria").join("Worlds"))
+	} else {
+		Some(user_dirs.home_dir().join(".local").join("share").join("Terraria").join("Worlds"))
+	}
+}
+
+pub fn find_worlds() -> Result<Vec<PathBuf>, ConfigError> {
+	let dir = worlds_dir().ok_or(ConfigError::NoWorldsFolder)?;
+	let entries = fs::read_dir(&dir).map_err(|e| ConfigError::IO(dir.clone(), e))?;
+
+	let mut worlds: Vec<PathBuf> = entries
+		.filter_map(Result::ok)
+		.map(|e| e.path())
+		.filter(|p| p.extension().is_some_and(|e| e == "wld"))
+		.collect();
+	worlds.sort();
+	Ok(worlds)
+}
+
+fn world_name(path: &Path) -> Option<String> {
+	Some(path.file_stem()?.to_string_lossy().into_owned())
+}
+
+// Terraria replaces spaces with underscores in world file names
+fn normalize_world_name(name: &str) -> String {
+	name.to_lowercase().replace(' ', "_")
+}
@@ -0,0 +1,70 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+	Error,
+	Warn,
+	Info,
+	Debug,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+pub fn set_level(level: LogLevel) {
+	LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn enabled(level: LogLevel) -> bool {
+	level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+impl FromStr for LogLevel {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_ascii_lowercase().as_str() {
+			"error" => Ok(Self::Error),
+			"warn" | "warning" => Ok(Self::Warn),
+			"info" => Ok(Self::Info),
+			"debug" => Ok(Self::Debug),
+			_ => Err(()),
+		}
+	}
+}
+
+#[macro_export]
+macro_rules! log_error {
+	($($arg:tt)*) => {
+		if $crate::log::enabled($crate::log::LogLevel::Error) {
+			eprintln!($($arg)*);
+		}
+	};
+}
+
+#[macro_export]
+macro_rules! log_warn {
+	($($arg:tt)*) => {
+		if $crate::log::enabled($crate::log::LogLevel::Warn) {
+			eprintln!($($arg)*);
+		}
+	};
+}
+
+#[macro_export]
+macro_rules! log_info {
+	($($arg:tt)*) => {
+		if $crate::log::enabled($crate::log::LogLevel::Info) {
+			println!($($arg)*);
+		}
+	};
+}
+
+#[macro_export]
+macro_rules! log_debug {
+	($($arg:tt)*) => {
+		if $crate::log::enabled($crate::log::LogLevel::Debug) {
+			println!($($arg)*);
+		}
+	};
+}
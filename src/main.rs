@@ -1,18 +1,23 @@
 #![allow(clippy::upper_case_acronyms)]
 
+#[macro_use]
+mod log;
+
 mod binary;
+mod config;
 mod network;
 mod world;
 
 use binary::reader::Reader;
+use config::{Config, USAGE};
 use network::server::Server;
-use std::path::Path;
+use std::{env, process};
 use world::{binary::FileReader, types::World};
 
 impl Drop for FileReader {
 	fn drop(&mut self) {
 		if self.cur < self.buf.len() {
-			println!(
+			log_debug!(
 				"dropped FileReader before EOI ({} bytes remaining)",
 				self.buf.len() - self.cur
 			)
@@ -23,7 +28,7 @@ impl Drop for FileReader {
 impl Drop for Reader<'_> {
 	fn drop(&mut self) {
 		if self.cur < self.buf.len() {
-			println!(
+			log_debug!(
 				"dropped Reader before EOI (code: {}, {} bytes remaining)",
 				self.buf[0],
 				self.buf.len() - self.cur
@@ -34,20 +39,37 @@ impl Drop for Reader<'_> {
 
 #[tokio::main]
 async fn main() {
-	let world = World::from_file(Path::new("C:\\Users\\Dim\\Documents\\My Games\\Terraria\\Worlds\\dim.wld")).unwrap();
-	// let world = World::from_file(Path::new("/mnt/c/Users/Dim/Documents/My Games/Terraria/Worlds/dim.wld")).unwrap();
-	// let world = World::from_file(Path::new("/Users/angelolloti/Library/Application Support/Terraria/Worlds/workshop.wld")).unwrap();
-	let srv = Server::new(world, "");
-	srv.listen("127.0.0.1:7778").await.unwrap();
-
-	// let Some(user_dirs) = UserDirs::new() else {
-	// 	panic!("couldn't find user dir")
-	// };
-
-	// let Some(doc_dir) = user_dirs.document_dir() else {
-	// 	panic!("couldn't find document dir")
-	// };
-
-	// let world_dir = doc_dir.join("My Games").join("Terraria").join("Worlds");
-	// let world_files = fs::read_dir(world_dir).unwrap();
+	let args: Vec<String> = env::args().skip(1).collect();
+	if args.iter().any(|a| a == "--help" || a == "-h") {
+		println!("{}", USAGE);
+		return;
+	}
+
+	let config = Config::from_args(&args).and_then(|c| Ok((c.world_path()?, c)));
+	let (path, config) = match config {
+		Ok(c) => c,
+		Err(e) => {
+			eprintln!("{}", e);
+			process::exit(1);
+		}
+	};
+	log::set_level(config.log_level);
+
+	log_info!("Loading world {}", path.display());
+	let world = match World::from_file(&path) {
+		Ok(world) => world,
+		Err(e) => {
+			eprintln!("Could not load {}: {}", path.display(), e);
+			process::exit(1);
+		}
+	};
+
+	let srv = Server::new(world, &path, &config);
+	if let Err(e) = srv.listen(&config.bind).await {
+		eprintln!("{}", e);
+		process::exit(1);
+	}
+
+	// The console is still blocked reading stdin, which would keep the runtime from shutting down
+	process::exit(0);
 }
@@ -0,0 +1,46 @@
+use crate::binary::types::Vector2;
+use crate::network::messages::Message;
+use crate::world::section::SectionChange;
+
+// Which players a broadcast concerns, checked by every connection task before forwarding it
+#[derive(Debug, Clone)]
+pub enum Scope {
+	// World state, chat and anything about players themselves
+	Everyone,
+	// Players that have the section of this tile loaded
+	Tile(usize, usize),
+	// Players that have some of this area loaded, as x, y, width and height
+	Area(usize, usize, usize, usize),
+	// Players that can see this position in pixels
+	Near(Vector2),
+}
+
+#[derive(Debug, Clone)]
+pub struct Broadcast {
+	pub msg: Message,
+	// Usually the player the message came from, who already knows about it
+	pub ignore: Option<usize>,
+	pub scope: Scope,
+	// The tile changes the message carries, which keep the sections of the players getting it current
+	pub changes: Vec<SectionChange>,
+}
+
+impl Broadcast {
+	pub fn new(msg: Message, ignore: Option<usize>, scope: Scope) -> Self {
+		Broadcast {
+			msg,
+			ignore,
+			scope,
+			changes: vec![],
+		}
+	}
+
+	pub fn with_changes(mut self, changes: Vec<SectionChange>) -> Self {
+		self.changes = changes;
+		self
+	}
+
+	pub fn everyone(msg: Message) -> Self {
+		Self::new(msg, None, Scope::Everyone)
+	}
+}
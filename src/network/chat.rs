@@ -0,0 +1,101 @@
+use rand::Rng;
+
+use crate::binary::types::{Text, RGB};
+use crate::network::broadcast::Broadcast;
+use crate::network::commands::CommandSource;
+use crate::network::messages::{Message, MessageDecodeError, NetModuleData};
+use crate::network::server::Server;
+
+pub const SERVER_AUTHOR: u8 = 255;
+// Colors from the vanilla chat commands
+pub const SERVER_COLOR: RGB = RGB(255, 240, 20);
+pub const ERROR_COLOR: RGB = RGB(255, 25, 25);
+const PLAYER_COLOR: RGB = RGB(255, 255, 255);
+const EMOTE_COLOR: RGB = RGB(200, 100, 0);
+
+pub fn chat_message(author: u8, text: Text, color: RGB) -> Result<Message, MessageDecodeError> {
+	NetModuleData::ChatMessage {
+		author,
+		text,
+		color,
+	}
+	.encode()
+}
+
+impl Server {
+	pub fn broadcast_chat(&self, text: Text, color: RGB) -> Result<(), MessageDecodeError> {
+		// Sending fails when nobody is connected, which is fine
+		let _ = self.broadcast.send(Broadcast::everyone(chat_message(SERVER_AUTHOR, text, color)?));
+		Ok(())
+	}
+
+	// Port of the ChatCommandProcessor commands the server is responsible for
+	// Must be called without holding the clients lock, / commands may need it
+	pub async fn handle_chat(
+		&self,
+		src: usize,
+		name: &str,
+		command: &str,
+		text: &str,
+	) -> anyhow::Result<Vec<Message>> {
+		match command {
+			"Say" if text.starts_with('/') => {
+				log_info!("{} used {}", name, text);
+				self.run_player_command(src, &text[1..]).await
+			}
+			"Say" if !text.trim().is_empty() => {
+				log_info!("<{}> {}", name, text);
+				let _ = self.broadcast.send(Broadcast::everyone(chat_message(
+					src as u8,
+					Text::Literal(text.to_owned()),
+					PLAYER_COLOR,
+				)?));
+				Ok(vec![])
+			}
+			"Emote" if !text.trim().is_empty() => {
+				log_info!("* {} {}", name, text);
+				self.broadcast_chat(
+					Text::Key(
+						"LegacyMultiplayer.6".to_owned(),
+						vec![Text::Literal(name.to_owned()), Text::Literal(text.to_owned())],
+					),
+					EMOTE_COLOR,
+				)?;
+				Ok(vec![])
+			}
+			"Roll" => {
+				let roll = rand::thread_rng().gen_range(1..=100);
+				self.broadcast_chat(
+					Text::Formattable(
+						"*{0} {1} {2}".to_owned(),
+						vec![
+							Text::Literal(name.to_owned()),
+							Text::Key("LegacyMultiplayer.9".to_owned(), vec![]),
+							Text::Literal(roll.to_string()),
+						],
+					),
+					SERVER_COLOR,
+				)?;
+				Ok(vec![])
+			}
+			"Playing" => self.run_player_command(src, "players").await,
+			"Help" => self.run_player_command(src, "help").await,
+			_ => {
+				log_debug!("Not yet implemented chat command {} from {}: {}", command, name, text);
+				Ok(vec![])
+			}
+		}
+	}
+
+	async fn run_player_command(&self, src: usize, line: &str) -> anyhow::Result<Vec<Message>> {
+		let (output, color) = match self.run_command(CommandSource::Player(src), line).await {
+			Ok(output) => (output, SERVER_COLOR),
+			Err(e) => (e.to_string(), ERROR_COLOR),
+		};
+
+		Ok(output
+			.lines()
+			.map(|line| chat_message(SERVER_AUTHOR, Text::Literal(line.to_owned()), color.clone()))
+			.collect::<Result<_, _>>()?)
+	}
+}
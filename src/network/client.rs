@@ -1,12 +1,22 @@
 use std::net::SocketAddr;
+use std::cmp::min;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
 
+use crate::binary::types::{Text, Vector2};
 use crate::world::transpiled::items;
+use crate::world::section::{SectionChange, SectionVersions};
 use crate::world::types::World;
+use crate::network::broadcast::Scope;
 use crate::network::messages::{self, PlayerItemSlot, Message, MessageDecodeError};
-use crate::network::utils::encode_tiles;
+use crate::network::outbox::Outbox;
+use crate::network::server::TILE;
+use crate::network::utils::{encode_tiles, get_section_x, get_section_y};
 use crate::network::transpiled::item_slots;
 
 pub const MAX_ITEM_SLOTS: usize = 350;
+// Twice NPC.sWidth, further than this a player is off screen even on the largest monitors
+const VIEW_RANGE: f32 = 3840.;
 
 #[derive(PartialEq, Eq)]
 #[repr(u8)]
@@ -18,8 +28,94 @@ pub enum ConnectionState {
 	Complete,
 }
 
+// What a player can see, shared with its connection task so broadcasts can be filtered without locking the clients
+pub struct ClientView {
+	// The version of each section the client was last sent, 0 for sections it doesn't have
+	sections: Vec<Vec<AtomicU32>>,
+	versions: Arc<SectionVersions>,
+	// The bits of the position's f32s
+	position: [AtomicU32; 2],
+}
+
+impl ClientView {
+	pub fn new(versions: Arc<SectionVersions>) -> Self {
+		let (width, height) = versions.size();
+		ClientView {
+			sections: (0..width).map(|_| (0..height).map(|_| AtomicU32::new(0)).collect()).collect(),
+			versions,
+			position: [AtomicU32::new(0), AtomicU32::new(0)],
+		}
+	}
+
+	pub fn has_section(&self, sec_x: usize, sec_y: usize) -> bool {
+		self.sections[sec_x][sec_y].load(Ordering::Relaxed) != 0
+	}
+
+	// Records that the client is getting the current version of the section, returns whether it already had it
+	pub fn sync_section(&self, sec_x: usize, sec_y: usize) -> bool {
+		let version = self.versions.get(sec_x, sec_y);
+		self.sections[sec_x][sec_y].swap(version, Ordering::Relaxed) == version
+	}
+
+	// Getting a change keeps a section current only if the client's copy was current right before it
+	// Changes that weren't broadcast leave the client behind, and the section is sent again once the player comes near
+	pub fn acknowledge(&self, changes: &[SectionChange]) {
+		for change in changes {
+			let section = &self.sections[change.x][change.y];
+			let _ = section.compare_exchange(change.from, change.to, Ordering::Relaxed, Ordering::Relaxed);
+		}
+	}
+
+	pub fn position(&self) -> Vector2 {
+		Vector2(
+			f32::from_bits(self.position[0].load(Ordering::Relaxed)),
+			f32::from_bits(self.position[1].load(Ordering::Relaxed)),
+		)
+	}
+
+	pub fn set_position(&self, position: &Vector2) {
+		self.position[0].store(position.0.to_bits(), Ordering::Relaxed);
+		self.position[1].store(position.1.to_bits(), Ordering::Relaxed);
+	}
+
+	// Makes the next PlayerAction send the sections around the player again, returns whether any were loaded
+	pub fn unload_sections(&self) -> bool {
+		let mut unloaded = false;
+		for column in &self.sections {
+			for section in column {
+				unloaded |= section.swap(0, Ordering::Relaxed) != 0;
+			}
+		}
+		unloaded
+	}
+
+	pub fn has_section_loaded(&self, x: usize, y: usize) -> bool {
+		self.has_section(get_section_x(x), get_section_y(y))
+	}
+
+	// Whether any section overlapping the area was sent to the client
+	pub fn has_area_loaded(&self, x: usize, y: usize, width: usize, height: usize) -> bool {
+		(get_section_x(x)..=get_section_x(x + width - 1))
+			.any(|i| (get_section_y(y)..=get_section_y(y + height - 1)).any(|j| self.has_section(i, j)))
+	}
+
+	pub fn concerns(&self, scope: &Scope) -> bool {
+		match scope {
+			Scope::Everyone => true,
+			Scope::Tile(x, y) => self.has_section_loaded(*x, *y),
+			Scope::Area(x, y, width, height) => self.has_area_loaded(*x, *y, *width, *height),
+			Scope::Near(p) => {
+				let position = self.position();
+				(position.0 - p.0).abs() < VIEW_RANGE && (position.1 - p.1).abs() < VIEW_RANGE
+			}
+		}
+	}
+}
+
 pub struct Client {
 	pub addr: SocketAddr,
+	// Messages meant for this client only, written by its writer task
+	pub outbox: Outbox,
 	pub state: ConnectionState,
 	pub uuid: Option<String>,
 	pub details: Option<messages::PlayerDetails>,
@@ -27,18 +123,27 @@ pub struct Client {
 	pub mana: Option<messages::PlayerMana>,
 	pub buffs: Option<messages::PlayerBuffs>,
 	pub items: Box<[Option<PlayerItemSlot>; MAX_ITEM_SLOTS]>,
-	pub loaded_sections: Vec<Vec<bool>>,
+	pub view: Arc<ClientView>,
 	pub selected_item: u8,
 	pub selected_loadout: u8,
+	// Player.chest, -1 when no chest is open
+	pub chest: i16,
+	// The tile entity whose interface the player has open, -1 when none
+	pub entity: i32,
 }
 
 impl Client {
-	pub fn new(addr: SocketAddr, width: usize, height: usize) -> Self {
+	pub fn new(
+		addr: SocketAddr,
+		outbox: Outbox,
+		view: Arc<ClientView>,
+	) -> Self {
 		// https://github.com/rust-lang/rust/issues/44796#issuecomment-967747810
 		const INIT_SLOT_NONE: Option<PlayerItemSlot> = None;
 
 		Self {
 			addr,
+			outbox,
 			state: ConnectionState::New,
 			details: None,
 			uuid: None,
@@ -46,12 +151,23 @@ impl Client {
 			buffs: None,
 			mana: None,
 			items: Box::new([INIT_SLOT_NONE; MAX_ITEM_SLOTS]),
-			loaded_sections: vec![vec![false; height]; width],
+			view,
 			selected_item: 0,
 			selected_loadout: 0,
+			chest: -1,
+			entity: -1,
 		}
 	}
 
+	pub fn send(&self, msg: Message) {
+		self.outbox.push(msg);
+	}
+
+	// The writer task closes the connection after sending this
+	pub fn kick(&self, reason: Text) {
+		self.send(Message::ConnectionRefuse(reason));
+	}
+
 	pub fn iter_inventory(&self) -> impl Iterator<Item = &Option<PlayerItemSlot>> {
 		self.items.iter().take(item_slots::INVENTORY_END + 1)
 	}
@@ -90,6 +206,10 @@ impl Client {
 		self.has_in_inventory(items::BLOWPIPE) || self.has_in_inventory(items::BLOWGUN)
 	}
 
+	pub fn has_section_loaded(&self, x: usize, y: usize) -> bool {
+		self.view.has_section_loaded(x, y)
+	}
+
 	pub fn encode_sections(
 		&mut self,
 		world: &World,
@@ -101,15 +221,25 @@ impl Client {
 		let mut msgs = vec![];
 		for x in sec_x_start..=sec_x_end {
 			for y in sec_y_start..=sec_y_end {
-				if self.loaded_sections[x][y] {
+				if self.view.sync_section(x, y) {
 					continue;
 				}
 
-				self.loaded_sections[x][y] = true;
 				msgs.push(encode_tiles(world, x, y)?)
 			}
 		}
 
 		Ok(msgs)
 	}
+
+	// The 3 by 3 sections around a position in pixels, minus the ones the client has an up to date copy of
+	pub fn encode_sections_near(&mut self, world: &World, position: &Vector2) -> Result<Vec<Message>, MessageDecodeError> {
+		let x_max = get_section_x(world.header.width as usize).saturating_sub(1);
+		let y_max = get_section_y(world.header.height as usize).saturating_sub(1);
+
+		let x = min(get_section_x((position.0.max(0.) / TILE) as usize), x_max);
+		let y = min(get_section_y((position.1.max(0.) / TILE) as usize), y_max);
+
+		self.encode_sections(world, x.saturating_sub(1), min(x + 1, x_max), y.saturating_sub(1), min(y + 1, y_max))
+	}
 }
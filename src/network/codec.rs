@@ -0,0 +1,95 @@
+use std::io::Cursor;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::network::messages::{Message, MessageDecodeError};
+
+// Every message starts with a u16 length that includes the length itself
+const LENGTH_SIZE: usize = 2;
+// The length and the message code
+const MIN_MESSAGE_LENGTH: usize = LENGTH_SIZE + 1;
+const MAX_MESSAGE_LENGTH: usize = u16::MAX as usize;
+const READ_CHUNK_SIZE: usize = 4096;
+
+pub struct FramedReader<R> {
+	inner: R,
+	buf: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> FramedReader<R> {
+	pub fn new(inner: R) -> Self {
+		Self {
+			inner,
+			buf: Vec::with_capacity(READ_CHUNK_SIZE),
+		}
+	}
+
+	// Returns None once the peer closes the connection on a message boundary
+	// Partial messages are kept in an internal buffer, so this is safe to use in select!
+	pub async fn next(&mut self) -> Result<Option<Message>, MessageDecodeError> {
+		loop {
+			if let Some(frame) = self.take_frame()? {
+				return Ok(Some(Message::try_from(frame)?));
+			}
+
+			let mut chunk = [0u8; READ_CHUNK_SIZE];
+			let read = self.inner.read(&mut chunk).await?;
+			if read == 0 {
+				return if self.buf.is_empty() {
+					Ok(None)
+				} else {
+					Err(MessageDecodeError::IO(std::io::ErrorKind::UnexpectedEof.into()))
+				};
+			}
+
+			self.buf.extend_from_slice(&chunk[..read]);
+		}
+	}
+
+	fn take_frame(&mut self) -> Result<Option<Vec<u8>>, MessageDecodeError> {
+		if self.buf.len() < LENGTH_SIZE {
+			return Ok(None);
+		}
+
+		let length = u16::from_le_bytes([self.buf[0], self.buf[1]]) as usize;
+		if !(MIN_MESSAGE_LENGTH..=MAX_MESSAGE_LENGTH).contains(&length) {
+			return Err(MessageDecodeError::InvalidLength(length));
+		}
+
+		if self.buf.len() < length {
+			return Ok(None);
+		}
+
+		let frame = self.buf[LENGTH_SIZE..length].to_vec();
+		self.buf.drain(..length);
+		Ok(Some(frame))
+	}
+}
+
+pub struct FramedWriter<W> {
+	inner: W,
+}
+
+impl<W: AsyncWrite + Unpin> FramedWriter<W> {
+	pub fn new(inner: W) -> Self {
+		Self { inner }
+	}
+
+	pub async fn send(&mut self, msg: Message) -> Result<(), MessageDecodeError> {
+		let frame = encode_frame(msg)?;
+		self.inner.write_all(&frame).await?;
+		Ok(())
+	}
+}
+
+pub fn encode_frame(msg: Message) -> Result<Vec<u8>, MessageDecodeError> {
+	let mut c = Cursor::new(vec![]);
+	msg.write(&mut c)?;
+	let frame = c.into_inner();
+	// MessageWriter truncates the length prefix, so anything bigger would desync the client
+	if frame.len() > MAX_MESSAGE_LENGTH {
+		return Err(MessageDecodeError::InvalidLength(frame.len()));
+	}
+
+	Ok(frame)
+}
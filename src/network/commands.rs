@@ -0,0 +1,473 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::{error::Error, fmt};
+use tokio::io::{self, AsyncBufReadExt, BufReader};
+
+use crate::binary::types::Text;
+use crate::network::broadcast::Broadcast;
+use crate::network::client::{Client, ConnectionState};
+use crate::network::chat::SERVER_COLOR;
+use crate::network::items::DroppedItem;
+use crate::network::messages::MessageDecodeError;
+use crate::network::server::Server;
+use crate::network::utils::encode_world_header;
+use crate::world::time::{DAY_LENGTH, NIGHT_LENGTH};
+use crate::world::types::ITEM_COUNT;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandSource {
+	Console,
+	Player(usize),
+}
+
+pub type CommandResult = Result<String, CommandError>;
+pub type CommandFuture<'a> = Pin<Box<dyn Future<Output = CommandResult> + Send + 'a>>;
+// Gets the arguments after the command name, split on whitespace
+pub type CommandHandler = for<'a> fn(&'a Server, CommandSource, Vec<String>) -> CommandFuture<'a>;
+
+pub struct Command {
+	pub name: &'static str,
+	pub usage: &'static str,
+	pub description: &'static str,
+	// Only the console can run admin commands
+	pub admin: bool,
+	pub handler: CommandHandler,
+}
+
+#[derive(Debug)]
+pub enum CommandError {
+	Unknown(String),
+	NotAllowed(String),
+	Usage(&'static str),
+	PlayerNotFound(String),
+	Failed(anyhow::Error),
+}
+
+impl fmt::Display for CommandError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Unknown(name) => write!(f, "Unknown command `{}`, try `help`", name),
+			Self::NotAllowed(name) => write!(f, "You are not allowed to use `{}`", name),
+			Self::Usage(usage) => write!(f, "Usage: {}", usage),
+			Self::PlayerNotFound(name) => write!(f, "No player named `{}`", name),
+			Self::Failed(err) => write!(f, "{}", err),
+		}
+	}
+}
+
+impl Error for CommandError {}
+
+impl From<anyhow::Error> for CommandError {
+	fn from(err: anyhow::Error) -> Self {
+		Self::Failed(err)
+	}
+}
+
+impl From<MessageDecodeError> for CommandError {
+	fn from(err: MessageDecodeError) -> Self {
+		Self::Failed(err.into())
+	}
+}
+
+pub struct CommandRegistry {
+	// Kept in registration order so help lists them predictably
+	commands: Vec<Command>,
+}
+
+impl CommandRegistry {
+	pub fn new() -> Self {
+		let mut registry = Self { commands: vec![] };
+		for command in builtin_commands() {
+			registry.register(command);
+		}
+		registry
+	}
+
+	// Registering a name twice replaces the earlier command
+	pub fn register(&mut self, command: Command) {
+		self.commands.retain(|c| c.name != command.name);
+		self.commands.push(command);
+	}
+
+	pub fn get(&self, name: &str) -> Option<&Command> {
+		self.commands.iter().find(|c| c.name.eq_ignore_ascii_case(name))
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = &Command> {
+		self.commands.iter()
+	}
+}
+
+impl Server {
+	#[allow(dead_code)]
+	pub async fn register_command(&self, command: Command) {
+		self.commands.write().await.register(command);
+	}
+
+	pub async fn run_command(&self, source: CommandSource, line: &str) -> CommandResult {
+		let mut args = line.split_whitespace().map(str::to_owned);
+		let Some(name) = args.next() else {
+			return Ok(String::new());
+		};
+
+		let handler = {
+			let commands = self.commands.read().await;
+			let Some(command) = commands.get(&name) else {
+				return Err(CommandError::Unknown(name));
+			};
+			if command.admin && source != CommandSource::Console {
+				return Err(CommandError::NotAllowed(name));
+			}
+			command.handler
+		};
+
+		handler(self, source, args.collect()).await
+	}
+
+	pub async fn run_console(self: Arc<Self>) {
+		let mut lines = BufReader::new(io::stdin()).lines();
+		while let Ok(Some(line)) = lines.next_line().await {
+			match self.run_command(CommandSource::Console, &line).await {
+				Ok(output) if !output.is_empty() => println!("{}", output),
+				Ok(_) => {}
+				Err(e) => println!("{}", e),
+			}
+		}
+	}
+}
+
+// Accepts either a client id or a player name
+fn find_player(clients: &[Option<Client>], name: &str) -> Option<usize> {
+	if let Ok(id) = name.parse::<usize>() {
+		if clients.get(id).is_some_and(Option::is_some) {
+			return Some(id);
+		}
+	}
+
+	clients.iter().position(|c| {
+		c.as_ref()
+			.and_then(|c| c.details.as_ref())
+			.is_some_and(|d| d.name.eq_ignore_ascii_case(name))
+	})
+}
+
+fn builtin_commands() -> Vec<Command> {
+	vec![
+		Command {
+			name: "help",
+			usage: "help",
+			description: "List the available commands",
+			admin: false,
+			handler: help,
+		},
+		Command {
+			name: "players",
+			usage: "players",
+			description: "List the connected players",
+			admin: false,
+			handler: players,
+		},
+		Command {
+			name: "queues",
+			usage: "queues",
+			description: "Show how far behind each player's outbound queue is",
+			admin: true,
+			handler: queues,
+		},
+		Command {
+			name: "resync",
+			usage: "resync <player>",
+			description: "Send a player the world around them again",
+			admin: true,
+			handler: resync,
+		},
+		Command {
+			name: "kick",
+			usage: "kick <player> [reason]",
+			description: "Disconnect a player",
+			admin: true,
+			handler: kick,
+		},
+		Command {
+			name: "ban",
+			usage: "ban <player>",
+			description: "Disconnect a player and refuse their address until the server restarts",
+			admin: true,
+			handler: ban,
+		},
+		Command {
+			name: "say",
+			usage: "say <text>",
+			description: "Send a message to every player",
+			admin: true,
+			handler: say,
+		},
+		Command {
+			name: "time",
+			usage: "time set <dawn|noon|dusk|midnight>",
+			description: "Change the time of day",
+			admin: true,
+			handler: time,
+		},
+		Command {
+			name: "save",
+			usage: "save",
+			description: "Save the world",
+			admin: true,
+			handler: save,
+		},
+		Command {
+			name: "password",
+			usage: "password [new password|-]",
+			description: "Show or change the server password, - removes it",
+			admin: true,
+			handler: password,
+		},
+		Command {
+			name: "exit",
+			usage: "exit",
+			description: "Save the world and shut down",
+			admin: true,
+			handler: exit,
+		},
+		Command {
+			name: "give",
+			usage: "give <player> <item id> [stack]",
+			description: "Drop an item on a player",
+			admin: true,
+			handler: give,
+		},
+	]
+}
+
+fn help(srv: &Server, source: CommandSource, _: Vec<String>) -> CommandFuture<'_> {
+	Box::pin(async move {
+		let commands = srv.commands.read().await;
+		Ok(commands
+			.iter()
+			.filter(|c| !c.admin || source == CommandSource::Console)
+			.map(|c| format!("{:<36} {}", c.usage, c.description))
+			.collect::<Vec<_>>()
+			.join("\n"))
+	})
+}
+
+fn players(srv: &Server, source: CommandSource, _: Vec<String>) -> CommandFuture<'_> {
+	Box::pin(async move {
+		let clients = srv.clients.lock().await;
+		let players: Vec<String> = clients
+			.iter()
+			.enumerate()
+			.filter_map(|(id, c)| {
+				let c = c.as_ref()?;
+				let name = c.details.as_ref().map_or("<joining>", |d| d.name.as_str());
+				// Players don't get to see each other's addresses
+				Some(if source == CommandSource::Console {
+					format!("{}: {} ({})", id, name, c.addr)
+				} else {
+					format!("{}: {}", id, name)
+				})
+			})
+			.collect();
+
+		Ok(if players.is_empty() {
+			"No players connected".to_owned()
+		} else {
+			players.join("\n")
+		})
+	})
+}
+
+fn queues(srv: &Server, _: CommandSource, _: Vec<String>) -> CommandFuture<'_> {
+	Box::pin(async move {
+		let clients = srv.clients.lock().await;
+		let queues: Vec<String> = clients
+			.iter()
+			.enumerate()
+			.filter_map(|(id, c)| {
+				let c = c.as_ref()?;
+				let name = c.details.as_ref().map_or("<joining>", |d| d.name.as_str());
+				let stats = c.outbox.stats();
+				Some(format!(
+					"{}: {} queued {}/{} (peak {}), sent {}, dropped {}, missed {}, resyncs {}",
+					id,
+					name,
+					c.outbox.len(),
+					c.outbox.capacity(),
+					stats.peak.load(Ordering::Relaxed),
+					stats.sent.load(Ordering::Relaxed),
+					stats.dropped.load(Ordering::Relaxed),
+					stats.missed.load(Ordering::Relaxed),
+					stats.resyncs.load(Ordering::Relaxed),
+				))
+			})
+			.collect();
+
+		Ok(if queues.is_empty() {
+			format!("No players connected, lag policy is {:?}", srv.lag_policy)
+		} else {
+			format!("Lag policy is {:?}\n{}", srv.lag_policy, queues.join("\n"))
+		})
+	})
+}
+
+fn resync(srv: &Server, _: CommandSource, args: Vec<String>) -> CommandFuture<'_> {
+	Box::pin(async move {
+		let Some(name) = args.first() else {
+			return Err(CommandError::Usage("resync <player>"));
+		};
+
+		let mut clients = srv.clients.lock().await;
+		let id = find_player(clients.as_slice(), name)
+			.ok_or_else(|| CommandError::PlayerNotFound(name.clone()))?;
+		let client = clients[id].as_mut().unwrap();
+		client.outbox.resync();
+
+		// Players still joining get their sections once they spawn
+		if client.state == ConnectionState::Complete {
+			let world = srv.world.read().await;
+			let position = client.view.position();
+			for msg in client.encode_sections_near(&world, &position)? {
+				client.send(msg);
+			}
+		}
+
+		Ok(format!("Resyncing {}", name))
+	})
+}
+
+fn kick(srv: &Server, _: CommandSource, args: Vec<String>) -> CommandFuture<'_> {
+	Box::pin(async move {
+		let Some(name) = args.first() else {
+			return Err(CommandError::Usage("kick <player> [reason]"));
+		};
+
+		let clients = srv.clients.lock().await;
+		let id = find_player(clients.as_slice(), name)
+			.ok_or_else(|| CommandError::PlayerNotFound(name.clone()))?;
+		let reason = if args.len() > 1 {
+			Text::Literal(args[1..].join(" "))
+		} else {
+			Text::Key("CLI.KickMessage".to_owned(), vec![])
+		};
+		clients[id].as_ref().unwrap().kick(reason);
+
+		Ok(format!("Kicked {}", name))
+	})
+}
+
+fn ban(srv: &Server, _: CommandSource, args: Vec<String>) -> CommandFuture<'_> {
+	Box::pin(async move {
+		let Some(name) = args.first() else {
+			return Err(CommandError::Usage("ban <player>"));
+		};
+
+		let clients = srv.clients.lock().await;
+		let id = find_player(clients.as_slice(), name)
+			.ok_or_else(|| CommandError::PlayerNotFound(name.clone()))?;
+		let client = clients[id].as_ref().unwrap();
+		srv.bans.lock().await.insert(client.addr.ip());
+		client.kick(Text::Key("CLI.BanMessage".to_owned(), vec![]));
+
+		Ok(format!("Banned {} ({})", name, client.addr.ip()))
+	})
+}
+
+fn say(srv: &Server, _: CommandSource, args: Vec<String>) -> CommandFuture<'_> {
+	Box::pin(async move {
+		if args.is_empty() {
+			return Err(CommandError::Usage("say <text>"));
+		}
+
+		let text = format!("<Server> {}", args.join(" "));
+		srv.broadcast_chat(Text::Literal(text.clone()), SERVER_COLOR)?;
+
+		Ok(text)
+	})
+}
+
+fn time(srv: &Server, _: CommandSource, args: Vec<String>) -> CommandFuture<'_> {
+	Box::pin(async move {
+		const USAGE: &str = "time set <dawn|noon|dusk|midnight>";
+		if args.len() != 2 || args[0] != "set" {
+			return Err(CommandError::Usage(USAGE));
+		}
+
+		// Same times as the vanilla console commands
+		let (day_time, time) = match args[1].as_str() {
+			"dawn" => (true, 0.),
+			"noon" => (true, DAY_LENGTH / 2.),
+			"dusk" => (false, 0.),
+			"midnight" => (false, NIGHT_LENGTH / 2.),
+			_ => return Err(CommandError::Usage(USAGE)),
+		};
+
+		let mut world = srv.world.write().await;
+		world.header.day_time = day_time;
+		world.header.time = time;
+		let _ = srv.broadcast.send(Broadcast::everyone(encode_world_header(&world.header)));
+
+		Ok(format!("Time set to {}", args[1]))
+	})
+}
+
+fn save(srv: &Server, _: CommandSource, _: Vec<String>) -> CommandFuture<'_> {
+	Box::pin(async move {
+		srv.save().await?;
+		Ok(String::new())
+	})
+}
+
+fn password(srv: &Server, _: CommandSource, args: Vec<String>) -> CommandFuture<'_> {
+	Box::pin(async move {
+		let mut password = srv.password.write().await;
+		match args.first().map(String::as_str) {
+			None if password.is_empty() => Ok("No password set".to_owned()),
+			None => Ok(format!("Password: {}", password)),
+			Some("-") => {
+				password.clear();
+				Ok("Password removed".to_owned())
+			}
+			Some(_) => {
+				*password = args.join(" ");
+				Ok(format!("Password set to {}", password))
+			}
+		}
+	})
+}
+
+fn exit(srv: &Server, _: CommandSource, _: Vec<String>) -> CommandFuture<'_> {
+	Box::pin(async move {
+		srv.exit.notify_one();
+		Ok(String::new())
+	})
+}
+
+fn give(srv: &Server, _: CommandSource, args: Vec<String>) -> CommandFuture<'_> {
+	Box::pin(async move {
+		const USAGE: &str = "give <player> <item id> [stack]";
+		let (Some(name), Some(item)) = (args.first(), args.get(1)) else {
+			return Err(CommandError::Usage(USAGE));
+		};
+		let item_id: i16 = item.parse().map_err(|_| CommandError::Usage(USAGE))?;
+		let stack: i16 = match args.get(2) {
+			Some(stack) => stack.parse().map_err(|_| CommandError::Usage(USAGE))?,
+			None => 1,
+		};
+		if !(1..ITEM_COUNT).contains(&item_id) || stack < 1 {
+			return Err(CommandError::Usage(USAGE));
+		}
+
+		let clients = srv.clients.lock().await;
+		let id = find_player(clients.as_slice(), name)
+			.ok_or_else(|| CommandError::PlayerNotFound(name.clone()))?;
+		let position = clients[id].as_ref().unwrap().view.position();
+
+		let item = DroppedItem::with_random_velocity(item_id, stack, 0, position);
+		srv.spawn_item(clients.as_slice(), item, None).await;
+
+		Ok(format!("Gave {} {} of item {}", name, stack, item_id))
+	})
+}
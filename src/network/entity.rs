@@ -0,0 +1,164 @@
+use std::mem;
+
+use crate::binary::types::Vector2;
+use crate::network::broadcast::{Broadcast, Scope};
+use crate::network::client::Client;
+use crate::network::items::DroppedItem;
+use crate::network::messages::{Message, NPCInfo};
+use crate::network::server::{Server, TILE};
+use crate::network::utils::{encode_entity, encode_removed_entity};
+use crate::world::entity::{entity_kind, Entity, EntityInner, EntityItem};
+use crate::world::types::{World, ITEM_COUNT};
+use crate::world::wiring::Hitbox;
+
+// NPCID.TargetDummy
+const DUMMY_NPC: i16 = 488;
+// NPC.width and NPC.height of the dummy
+const DUMMY_SIZE: Vector2 = Vector2(18., 40.);
+
+// Port of NetMessage case 23 for a dummy, which stands still at full health
+// The dummy finds its entity through the position in its first two ai slots
+pub fn encode_dummy_npc(npc: i16, entity: &Entity) -> Message {
+	// NPC.NewNPC centers the NPC on the bottom of the 2x3 dummy
+	let (x, y) = (entity.x as f32 * TILE + 16., entity.y as f32 * TILE + 48.);
+	Message::NPCInfo(NPCInfo {
+		id: npc,
+		position: Vector2(x - DUMMY_SIZE.0 / 2., y - DUMMY_SIZE.1),
+		velocity: Vector2(0., 0.),
+		target: 0,
+		flags_1: 0b1000_1100, // ai[0], ai[1] and full health
+		flags_2: 0,
+		npc_ai: vec![entity.x as f32, entity.y as f32],
+		id_2: DUMMY_NPC,
+		stats_scaled_for_n_players: None,
+		strength_multiplier: None,
+		life_len: None,
+		life_i8: None,
+		life_i16: None,
+		life_i32: None,
+		release_owner: None,
+	})
+}
+
+// An NPC with no health, which clients deactivate
+pub fn encode_removed_npc(npc: i16) -> Message {
+	Message::NPCInfo(NPCInfo {
+		id: npc,
+		position: Vector2(0., 0.),
+		velocity: Vector2(0., 0.),
+		target: 0,
+		flags_1: 0,
+		flags_2: 0,
+		npc_ai: vec![],
+		id_2: 0,
+		stats_scaled_for_n_players: None,
+		strength_multiplier: None,
+		life_len: Some(1),
+		life_i8: Some(0),
+		life_i16: None,
+		life_i32: None,
+		release_owner: None,
+	})
+}
+
+// Turns an item a client sent into what an entity holds, None when the item can't exist
+pub fn entity_item(id: i16, stack: i16, prefix: u8) -> Option<EntityItem> {
+	if !(0..ITEM_COUNT).contains(&id) || stack < 0 {
+		return None;
+	}
+	Some(if id == 0 || stack == 0 { EntityItem::default() } else { EntityItem { id, stack, prefix } })
+}
+
+impl Server {
+	// Sends the entity to everyone that has its tile loaded
+	pub fn sync_entity(&self, entity: &Entity) {
+		let scope = Scope::Tile(entity.x as usize, entity.y as usize);
+		match encode_entity(entity) {
+			// Sending fails when nobody is connected, which is fine
+			Ok(msg) => {
+				let _ = self.broadcast.send(Broadcast::new(msg, None, scope));
+			}
+			Err(e) => log_warn!("Failed to encode tile entity {}: {}", entity.id, e),
+		}
+	}
+
+	// Tells everyone an entity is gone and drops whatever it held
+	pub async fn remove_entity(&self, clients: &mut [Option<Client>], entity: Entity) {
+		let scope = Scope::Tile(entity.x as usize, entity.y as usize);
+		let _ = self.broadcast.send(Broadcast::new(encode_removed_entity(entity.id), None, scope));
+
+		if let EntityInner::Dummy { npc } = entity.inner {
+			if npc != -1 {
+				let _ = self.broadcast.send(Broadcast::everyone(encode_removed_npc(npc)));
+			}
+		}
+		for c in clients.iter_mut().flatten().filter(|c| c.entity == entity.id) {
+			c.entity = -1;
+		}
+
+		let position = Vector2(entity.x as f32 * TILE, entity.y as f32 * TILE);
+		for item in entity.inner.items() {
+			let item = DroppedItem::with_random_velocity(item.id, item.stack, item.prefix, position.clone());
+			self.spawn_item(clients, item, None).await;
+		}
+	}
+
+	// Spawns and despawns dummy NPCs as players come and go, every player knows about every NPC
+	pub fn run_dummies(&self, world: &mut World, players: &[Hitbox]) {
+		for (i, old) in world.update_dummies(players) {
+			let entity = &world.entities[i];
+			if old != -1 {
+				let _ = self.broadcast.send(Broadcast::everyone(encode_removed_npc(old)));
+			}
+			if let EntityInner::Dummy { npc } = entity.inner {
+				if npc != -1 {
+					let _ = self.broadcast.send(Broadcast::everyone(encode_dummy_npc(npc, entity)));
+				}
+			}
+			self.sync_entity(entity);
+		}
+	}
+
+	// Port of TEItemFrame.TryPlacing, TEWeaponsRack.TryPlacing and TEFoodPlatter.TryPlacing
+	// The item already on display drops, and taking it down is placing nothing
+	pub async fn place_entity_item(
+		&self,
+		clients: &[Option<Client>],
+		src: usize,
+		(x, y): (i16, i16),
+		host: i16,
+		(id, stack, prefix): (i16, i16, u8),
+	) {
+		let mut world = self.world.write().await;
+		let Some(client) = clients[src].as_ref() else {
+			return;
+		};
+		if !world.in_world(x as i32, y as i32, 1) || !client.has_section_loaded(x as usize, y as usize) {
+			return;
+		}
+		let Some(item) = entity_item(id, stack, prefix) else {
+			return;
+		};
+		let Some(i) = world
+			.object_at(x as usize, y as usize)
+			.and_then(|(x, y, _, _)| world.find_entity(x, y))
+			.filter(|&i| Some(world.entities[i].inner.kind()) == entity_kind(host))
+		else {
+			return;
+		};
+
+		let entity = &mut world.entities[i];
+		let (EntityInner::ItemFrame(held) | EntityInner::WeaponsRack(held) | EntityInner::FoodPlatter(held)) =
+			&mut entity.inner
+		else {
+			return;
+		};
+		let old = mem::replace(held, item);
+		self.sync_entity(entity);
+		if !old.is_empty() {
+			let position = Vector2(entity.x as f32 * TILE, entity.y as f32 * TILE);
+			let item = DroppedItem::with_random_velocity(old.id, old.stack, old.prefix, position);
+			self.spawn_item(clients, item, None).await;
+		}
+	}
+}
@@ -0,0 +1,240 @@
+use rand::random;
+use std::time::{Duration, Instant};
+
+use crate::binary::types::Vector2;
+use crate::network::broadcast::Broadcast;
+use crate::network::client::{Client, ConnectionState};
+use crate::network::messages::{DropItem, Message, PlayerReserveItem};
+use crate::network::server::Server;
+
+pub const MAX_ITEMS: usize = 400; // Main.maxItems
+// Clients send new items with this id and let the server pick the real one
+pub const NEW_ITEM: i16 = MAX_ITEMS as i16;
+pub const NO_OWNER: u8 = 255;
+// Item.ownTime, 100 ticks during which the player that dropped an item can't pick it back up
+const OWN_IGNORE_TIME: Duration = Duration::from_millis(100 * 1000 / 60);
+// NPC.sWidth, players further away than this never own an item
+const OWNER_RANGE: f32 = 1920.;
+// Player.width and Player.height
+pub const PLAYER_SIZE: Vector2 = Vector2(20., 42.);
+
+#[derive(Debug, Clone)]
+pub struct DroppedItem {
+	pub item_id: i16,
+	pub stack: i16,
+	pub prefix: u8,
+	pub position: Vector2,
+	pub velocity: Vector2,
+	// The client that simulates the item and is the only one allowed to pick it up
+	pub owner: u8,
+	own_ignore: Option<(usize, Instant)>,
+	// DereserveItem was sent to the owner and we're waiting for it to let go
+	releasing: bool,
+	spawned: Instant,
+}
+
+impl DroppedItem {
+	pub fn new(item_id: i16, stack: i16, prefix: u8, position: Vector2, velocity: Vector2) -> Self {
+		DroppedItem {
+			item_id,
+			stack,
+			prefix,
+			position,
+			velocity,
+			owner: NO_OWNER,
+			own_ignore: None,
+			releasing: false,
+			spawned: Instant::now(),
+		}
+	}
+
+	// Random velocity of items popping out of broken tiles, from Item.NewItem
+	pub fn with_random_velocity(item_id: i16, stack: i16, prefix: u8, position: Vector2) -> Self {
+		let velocity = Vector2(random::<f32>() * 6. - 3., (random::<f32>() * -2.5) - 1.5);
+		Self::new(item_id, stack, prefix, position, velocity)
+	}
+
+	pub fn encode(&self, id: usize) -> Message {
+		Message::DropItem(DropItem {
+			id: id as i16,
+			position: self.position.clone(),
+			velocity: self.velocity.clone(),
+			stack: self.stack,
+			prefix: self.prefix,
+			own_ignore: false,
+			item_id: self.item_id,
+		})
+	}
+
+	// Distance from the player's center, the way Item.FindOwner measures it
+	fn distance(&self, c: &Client) -> f32 {
+		let position = c.view.position();
+		(position.0 + PLAYER_SIZE.0 / 2. - self.position.0).abs()
+			+ (position.1 + PLAYER_SIZE.1 / 2. - self.position.1).abs()
+	}
+
+	fn ignores(&self, client: usize) -> bool {
+		self.own_ignore.is_some_and(|(c, until)| c == client && Instant::now() < until)
+	}
+}
+
+pub fn encode_removed_item(id: usize) -> Message {
+	Message::DropItem(DropItem {
+		id: id as i16,
+		position: Vector2(0., 0.),
+		velocity: Vector2(0., 0.),
+		stack: 0,
+		prefix: 0,
+		own_ignore: false,
+		item_id: 0,
+	})
+}
+
+pub fn encode_item_owner(id: usize, owner: u8) -> Message {
+	Message::PlayerReserveItem(PlayerReserveItem {
+		id: id as i16,
+		client_id: owner,
+	})
+}
+
+// Port of the server side of Main.item
+pub struct ItemPool {
+	items: Vec<Option<DroppedItem>>,
+}
+
+impl ItemPool {
+	pub fn new() -> Self {
+		ItemPool {
+			items: vec![None; MAX_ITEMS],
+		}
+	}
+
+	pub fn get(&self, id: usize) -> Option<&DroppedItem> {
+		self.items.get(id)?.as_ref()
+	}
+
+	pub fn get_mut(&mut self, id: usize) -> Option<&mut DroppedItem> {
+		self.items.get_mut(id)?.as_mut()
+	}
+
+	pub fn remove(&mut self, id: usize) -> Option<DroppedItem> {
+		self.items.get_mut(id)?.take()
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (usize, &DroppedItem)> {
+		self.items.iter().enumerate().filter_map(|(i, item)| Some((i, item.as_ref()?)))
+	}
+
+	// Like Item.NewItem, the oldest item makes room when every slot is taken
+	pub fn insert(&mut self, item: DroppedItem) -> usize {
+		let id = match self.items.iter().position(Option::is_none) {
+			Some(id) => id,
+			None => self
+				.iter()
+				.min_by_key(|(_, item)| item.spawned)
+				.map(|(id, _)| id)
+				.unwrap(),
+		};
+		self.items[id] = Some(item);
+		id
+	}
+
+	// Port of Item.FindOwner, returns the message announcing the new owner if it changed
+	pub fn find_owner(&mut self, id: usize, clients: &[Option<Client>]) -> Option<Message> {
+		let item = self.get_mut(id)?;
+		let old = item.owner;
+
+		item.owner = NO_OWNER;
+		let mut nearest = OWNER_RANGE;
+		for (i, c) in clients.iter().enumerate() {
+			let Some(c) = c.as_ref().filter(|c| c.state == ConnectionState::Complete) else {
+				continue;
+			};
+			if item.ignores(i) {
+				continue;
+			}
+
+			let distance = item.distance(c);
+			if distance < nearest {
+				nearest = distance;
+				item.owner = i as u8;
+			}
+		}
+
+		(item.owner != old).then(|| encode_item_owner(id, item.owner))
+	}
+
+	// Hands unowned items to the nearest player and asks owners that wandered off to let go
+	pub fn update_owners(&mut self, clients: &[Option<Client>]) -> Vec<Message> {
+		let mut res = vec![];
+		for id in 0..MAX_ITEMS {
+			let Some(item) = self.get_mut(id) else {
+				continue;
+			};
+
+			let owner = clients
+				.get(item.owner as usize)
+				.and_then(Option::as_ref)
+				.filter(|c| c.state == ConnectionState::Complete);
+			match owner {
+				// Nobody owns the item or its owner left
+				None => {
+					item.releasing = false;
+					res.extend(self.find_owner(id, clients));
+				}
+				Some(c) if !item.releasing => {
+					if item.distance(c) > OWNER_RANGE {
+						item.releasing = true;
+						c.send(Message::DereserveItem(id as i16));
+					}
+				}
+				Some(_) => {}
+			}
+		}
+		res
+	}
+
+	// The owner answered DereserveItem, the item is up for grabs until the next update
+	pub fn release(&mut self, id: usize, src: usize) -> Option<Message> {
+		let item = self.get_mut(id).filter(|item| item.owner as usize == src)?;
+		item.owner = NO_OWNER;
+		item.releasing = false;
+		Some(encode_item_owner(id, NO_OWNER))
+	}
+
+	// The DropItem and PlayerReserveItem pairs SpawnRequest sends to joining players
+	pub fn encode_items(&self) -> Vec<Message> {
+		self.iter()
+			.flat_map(|(id, item)| [item.encode(id), encode_item_owner(id, item.owner)])
+			.collect()
+	}
+}
+
+impl Server {
+	// Adds an item to the world and tells everyone about it
+	// own_ignore keeps the player that dropped the item from picking it back up right away
+	pub async fn spawn_item(
+		&self,
+		clients: &[Option<Client>],
+		mut item: DroppedItem,
+		own_ignore: Option<usize>,
+	) -> usize {
+		item.own_ignore = own_ignore.map(|c| (c, Instant::now() + OWN_IGNORE_TIME));
+
+		let mut items = self.items.lock().await;
+		let id = items.insert(item);
+		// Sending fails when nobody is connected, which is fine
+		let _ = self.broadcast.send(Broadcast::everyone(items.get(id).unwrap().encode(id)));
+		if let Some(msg) = items.find_owner(id, clients) {
+			let _ = self.broadcast.send(Broadcast::everyone(msg));
+		}
+		id
+	}
+
+	pub async fn update_item_owners(&self) {
+		let clients = self.clients.lock().await;
+		for msg in self.items.lock().await.update_owners(clients.as_slice()) {
+			let _ = self.broadcast.send(Broadcast::everyone(msg));
+		}
+	}
+}
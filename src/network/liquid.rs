@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+
+use crate::network::broadcast::{Broadcast, Scope};
+use crate::network::messages::{LiquidChange, NetModuleData};
+use crate::network::server::{send_tile_squares, Server};
+use crate::network::utils::{get_section_x, get_section_y};
+use crate::world::liquid::LiquidChanges;
+use crate::world::types::World;
+
+// Keeps the messages well under the 64KiB frame limit, each change takes 6 bytes
+const MAX_CHANGES_PER_MESSAGE: usize = 8000;
+
+impl Server {
+	// Port of NetLiquidModule.PrepareAndSendToEachPlayerSeparately, changes are batched per section
+	// and only reach the players that have that section loaded
+	pub fn sync_liquids(&self, world: &mut World, changes: LiquidChanges, snapshot: &[Vec<u32>]) {
+		let mut sections: HashMap<(usize, usize), Vec<LiquidChange>> = HashMap::new();
+		for (x, y) in changes.changed {
+			let tile = &world.tiles[x][y];
+			sections
+				.entry((get_section_x(x), get_section_y(y)))
+				.or_default()
+				.push((x as u16, y as u16, tile.liquid, tile.liquid_kind.id()));
+		}
+
+		for cells in sections.into_values() {
+			for chunk in cells.chunks(MAX_CHANGES_PER_MESSAGE) {
+				// Any cell of the batch stands for its section
+				let scope = Scope::Tile(chunk[0].0 as usize, chunk[0].1 as usize);
+				match NetModuleData::Liquids(chunk.to_vec()).encode() {
+					// Sending fails when nobody is connected, which is fine
+					Ok(msg) => {
+						let _ = self.broadcast.send(Broadcast::new(msg, None, scope));
+					}
+					Err(e) => log_warn!("Failed to encode liquid changes: {}", e),
+				}
+			}
+		}
+
+		// Blocks made by mixing liquids, along with the neighbours they reframe
+		let mut areas = vec![];
+		for (x, y) in changes.merged {
+			world.frame_area(x, y, 1, 1);
+			areas.push((x - 1, y - 1, 3, 3));
+		}
+		if let Err(e) = send_tile_squares(&self.broadcast, world, &areas, snapshot) {
+			log_warn!("Failed to send merged liquids: {}", e);
+		}
+	}
+}
@@ -1,15 +1,12 @@
 use crate::binary::reader::Reader;
-use crate::binary::types::{Text, Vector2, RGB};
-use crate::binary::writer::MessageWriter;
+use crate::binary::types::{ReadError, Text, Vector2, RGB};
+use crate::binary::writer::{MessageWriter, Writer};
+use crate::world::chest::CHEST_NAME_LENGTH;
 use macros::message_encoder_decoder;
-use std::io::Cursor;
-
-use tokio::io::{AsyncWrite, AsyncWriteExt};
 
 use std::error::Error;
 use std::io;
 use std::io::{Seek, Write};
-use std::pin::Pin;
 
 pub trait Sanitize {
 	fn sanitize(&mut self, src: u8);
@@ -104,19 +101,110 @@ impl Sanitize for PlayerTalkNPC {
 	}
 }
 
+impl Sanitize for DisplayDollItem {
+	fn sanitize(&mut self, src: u8) {
+		self.client_id = src;
+	}
+}
+
+impl Sanitize for TileEntityInteraction {
+	fn sanitize(&mut self, src: u8) {
+		self.client_id = src;
+	}
+}
+
+impl Sanitize for HatRackItem {
+	fn sanitize(&mut self, src: u8) {
+		self.client_id = src;
+	}
+}
+
 pub trait CustomDecode {
-	fn decode(&mut self, r: &mut Reader);
+	fn decode(&mut self, r: &mut Reader) -> Result<(), ReadError>;
 }
 
 impl CustomDecode for PlayerAction {
-	fn decode(&mut self, r: &mut Reader) {
+	fn decode(&mut self, r: &mut Reader) -> Result<(), ReadError> {
 		if self.flags_2 & 0b100 != 0 {
-			self.velocity = Some(r.read_vector2());
+			self.velocity = Some(r.read_vector2()?);
 		}
 		if self.flags_3 & 0b1000000 != 0 {
-			self.pot_of_ret_origin_position = Some(r.read_vector2());
-			self.pot_of_ret_home_position = Some(r.read_vector2());
+			self.pot_of_ret_origin_position = Some(r.read_vector2()?);
+			self.pot_of_ret_home_position = Some(r.read_vector2()?);
 		}
+		Ok(())
+	}
+}
+
+// NetManager module ids, from the order modules are registered in Main.Initialize
+pub const NET_MODULE_LIQUID: u16 = 0;
+pub const NET_MODULE_TEXT: u16 = 1;
+
+// x, y, amount and Tile.liquidType
+pub type LiquidChange = (u16, u16, u8, u8);
+
+// The payload of a NetModule message, depending on its module id
+#[derive(Debug, Clone)]
+pub enum NetModuleData {
+	/// <- A chat message, command is the chat command id ("Say", "Emote", "Roll", ...)
+	ChatRequest { command: String, text: String },
+	/// -> A line of chat, author is the sending player or 255 for the server
+	ChatMessage { author: u8, text: Text, color: RGB },
+	/// -> Liquid of changed tiles
+	Liquids(Vec<LiquidChange>),
+}
+
+impl NetModuleData {
+	// Returns None for modules the server doesn't handle yet
+	pub fn decode(module: u16, payload: &[u8]) -> Result<Option<Self>, ReadError> {
+		let mut r = Reader::new(payload);
+		Ok(match module {
+			NET_MODULE_TEXT => Some(Self::ChatRequest {
+				command: r.read_string()?,
+				text: r.read_string()?,
+			}),
+			_ => None,
+		})
+	}
+
+	pub fn encode(self) -> Result<Message, MessageDecodeError> {
+		let mut w = Writer::new(vec![]);
+		let module = match self {
+			Self::ChatMessage {
+				author,
+				text,
+				color,
+			} => {
+				w.write_byte(author)?;
+				w.write_text(text)?;
+				w.write_rgb(color)?;
+				NET_MODULE_TEXT
+			}
+			Self::Liquids(changes) => {
+				w.write_u16(changes.len() as u16)?;
+				for (x, y, amount, kind) in changes {
+					w.write_i32(((x as i32) << 16) | y as i32)?;
+					w.write_byte(amount)?;
+					w.write_byte(kind)?;
+				}
+				NET_MODULE_LIQUID
+			}
+			Self::ChatRequest { .. } => return Err(MessageDecodeError::Unserializable),
+		};
+
+		Ok(Message::NetModule(NetModule {
+			module,
+			payload: w.into_inner(),
+		}))
+	}
+}
+
+impl CustomDecode for SyncPlayerChest {
+	fn decode(&mut self, r: &mut Reader) -> Result<(), ReadError> {
+		if self.name_length != 0 && self.name_length as usize <= CHEST_NAME_LENGTH {
+			self.name = Some(r.read_string()?);
+		}
+		Ok(())
 	}
 }
 
@@ -293,6 +381,15 @@ pub enum Message {
 		sun_mod_y: i16,
 		moon_mod_y: i16,
 	},
+	/// 20 <->
+	TileSquare {
+		x: i16,
+		y: i16,
+		width: u8,
+		height: u8,
+		change_type: u8, // TileChangeType, 0 for none
+		tiles: Vec<u8>,
+	},
 	/// 21 <->
 	DropItem {
 		id: i16,
@@ -304,7 +401,7 @@ pub enum Message {
 		item_id: i16,
 	},
 	/// 22 <->
-	PlayerReserveItem { time: i16, client_id: u8 },
+	PlayerReserveItem { id: i16, client_id: u8 }, // The item and the client it's reserved for, 255 for nobody
 	/// 23 ->
 	NPCInfo {
 		id: i16,
@@ -323,6 +420,40 @@ pub enum Message {
 		life_i32: Option<i32>,
 		release_owner: Option<u8>,
 	},
+	/// 28 <->
+	StrikeNPC {
+		npc: i16,
+		damage: i16,
+		knockback: f32,
+		direction: u8, // Hit direction plus one
+		crit: u8,
+	},
+	/// 31 <-
+	RequestChestOpen { x: i16, y: i16 },
+	/// 32 <->
+	SyncChestItem {
+		chest: i16,
+		slot: u8,
+		stack: i16,
+		prefix: u8,
+		item_id: i16,
+	},
+	/// 33 <-> custom_decode
+	SyncPlayerChest {
+		chest: i16,
+		x: i16,
+		y: i16,
+		name_length: u8, // 0 keeps the name, 255 clears it
+		name: Option<String>,
+	},
+	/// 34 <->
+	PlaceChest {
+		action: u8, // PlaceChest = 0, KillChest = 1, PlaceDresser = 2, KillDresser = 3, PlaceContainers2 = 4, KillContainers2 = 5
+		x: i16,
+		y: i16,
+		style: i16,
+		chest: i16,
+	},
 	/// 37 ->
 	PasswordRequest,
 	/// 38 <-
@@ -337,6 +468,24 @@ pub enum Message {
 		current: i16,
 		maximum: i16,
 	},
+	/// 46 <-
+	ReadSign { x: i16, y: i16 },
+	/// 47 <->
+	UpdateSign {
+		sign: i16,
+		x: i16,
+		y: i16,
+		text: String,
+		client_id: u8, // The player that edited the sign, or that the sign gets opened for
+		flags: u8,
+	},
+	/// 48 <->
+	LiquidUpdate {
+		x: i16,
+		y: i16,
+		liquid: u8,
+		liquid_kind: u8,
+	},
 	/// 49 ->
 	PlayerSyncDone,
 	/// 50 <->
@@ -352,6 +501,13 @@ pub enum Message {
 	ToggleSwitch { x: i16, y: i16 },
 	/// 68 <-
 	UUID(String),
+	/// 69 ->
+	ChestName {
+		chest: i16,
+		x: i16,
+		y: i16,
+		name: String,
+	},
 	/// 74 ->
 	AnglerQuest { id: u8, finished: bool },
 	/// 78 ->
@@ -361,8 +517,28 @@ pub enum Message {
 		icon: i8,
 		progress_wave: i8,
 	},
+	/// 80 ->
+	SyncChestUser { client_id: u8, chest: i16 },
+	/// 82 <->
+	NetModule { module: u16, payload: Vec<u8> },
 	/// 83 ->
 	KillCount { id: i16, amount: i32 },
+	/// 86 ->
+	UpdateTileEntity {
+		id: i32,
+		exists: bool,
+		entity: Vec<u8>, // Kind, position and data as in the world file but without the id, empty when the entity was removed
+	},
+	/// 87 <-
+	PlaceTileEntity { x: i16, y: i16, kind: u8 },
+	/// 89 <-
+	PlaceItemFrameItem {
+		x: i16,
+		y: i16,
+		item_id: i16,
+		prefix: u8,
+		stack: i16,
+	},
 	/// 101 ->
 	PillarShieldStrengths {
 		solar: u16,
@@ -372,6 +548,40 @@ pub enum Message {
 	},
 	/// 103 ->
 	MoonlordCountdown { maximum: i32, current: i32 },
+	/// 107 ->
+	SmartTextMessage {
+		color: RGB,
+		text: Text,
+		width_limit: i16,
+	},
+	/// 121 <->
+	DisplayDollItem {
+		client_id: u8,
+		entity: i32,
+		slot: u8, // Dyes come after the items
+		item_id: i16,
+		stack: i16,
+		prefix: u8,
+	},
+	/// 122 <->
+	TileEntityInteraction { entity: i32, client_id: u8 }, // -1 when the player stops interacting
+	/// 123 <-
+	PlaceWeaponsRackItem {
+		x: i16,
+		y: i16,
+		item_id: i16,
+		prefix: u8,
+		stack: i16,
+	},
+	/// 124 <->
+	HatRackItem {
+		client_id: u8,
+		entity: i32,
+		slot: u8, // Dyes come after the items
+		item_id: i16,
+		stack: i16,
+		prefix: u8,
+	},
 	/// 125 <->
 	PlayerPickTile {
 		client_id: u8,
@@ -381,6 +591,14 @@ pub enum Message {
 	},
 	/// 129 ->
 	PlayerSpawnResponse,
+	/// 133 <-
+	PlaceFoodPlatterItem {
+		x: i16,
+		y: i16,
+		item_id: i16,
+		prefix: u8,
+		stack: i16,
+	},
 	/// 136 ->
 	MonsterTypes([u16; 6]),
 	/// 138 <-
@@ -395,23 +613,15 @@ pub enum Message {
 	Custom(u8, Vec<u8>),
 }
 
-impl Message {
-	pub async fn write_stream(
-		self,
-		mut stream: Pin<&mut impl AsyncWrite>,
-	) -> Result<usize, MessageDecodeError> {
-		let mut c = Cursor::new(vec![]);
-		self.write(&mut c)?;
-		stream
-			.write(&c.into_inner())
-			.await
-			.map_err(MessageDecodeError::IO)
-	}
-}
-
 #[derive(Debug)]
 pub enum MessageDecodeError {
 	Unserializable,
+	Malformed {
+		code: u8,
+		offset: usize,
+		error: ReadError,
+	},
+	InvalidLength(usize),
 	IO(io::Error),
 }
 
@@ -419,6 +629,12 @@ impl std::fmt::Display for MessageDecodeError {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		match self {
 			Self::Unserializable => write!(f, "Unserializable enum variant."),
+			Self::Malformed {
+				code,
+				offset,
+				error,
+			} => write!(f, "Malformed message {} at offset {}: {}", code, offset, error),
+			Self::InvalidLength(len) => write!(f, "Invalid message length {}", len),
 			Self::IO(e) => write!(f, "Got IO error: {}", e),
 		}
 	}
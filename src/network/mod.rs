@@ -1,5 +1,16 @@
-pub mod client;
-pub mod messages;
-pub mod server;
-pub mod utils;
-pub mod transpiled;
+pub mod broadcast;
+pub mod chat;
+pub mod client;
+pub mod codec;
+pub mod commands;
+pub mod entity;
+pub mod items;
+pub mod liquid;
+pub mod messages;
+pub mod outbox;
+pub mod save;
+pub mod server;
+pub mod tick;
+pub mod utils;
+pub mod wiring;
+pub mod transpiled;
@@ -0,0 +1,216 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWrite;
+use tokio::select;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::Notify;
+use tokio::time::timeout;
+
+use crate::binary::types::Text;
+use crate::network::client::ClientView;
+use crate::network::codec::FramedWriter;
+use crate::network::messages::Message;
+
+pub const DEFAULT_QUEUE_SIZE: usize = 256;
+// How long a client kicked for lagging gets to receive the reason before the connection is dropped
+const KICK_TIMEOUT: Duration = Duration::from_secs(1);
+
+// What to do when a client's queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LagPolicy {
+	// Drop messages that the next one makes up for, resync when anything else would be lost
+	Drop,
+	// Resync on any lost message
+	Resync,
+	// Disconnect the client
+	Kick,
+}
+
+impl FromStr for LagPolicy {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_ascii_lowercase().as_str() {
+			"drop" => Ok(Self::Drop),
+			"resync" => Ok(Self::Resync),
+			"kick" => Ok(Self::Kick),
+			_ => Err(()),
+		}
+	}
+}
+
+// Positions, stats and effects get sent again soon enough, everything else changes state clients keep
+fn is_critical(msg: &Message) -> bool {
+	!matches!(
+		msg,
+		Message::PlayerAction(_)
+			| Message::PlayerHealth(_)
+			| Message::UpdateTime(_)
+			| Message::PlayerMana(_)
+			| Message::PlayInstrument(_)
+			| Message::PlayerPickTile(_)
+	)
+}
+
+#[derive(Debug, Default)]
+pub struct OutboxStats {
+	// Messages written to the connection
+	pub sent: AtomicU64,
+	// Messages that didn't fit in the queue
+	pub dropped: AtomicU64,
+	// Broadcasts the connection task fell too far behind to see
+	pub missed: AtomicU64,
+	pub resyncs: AtomicU64,
+	// Most messages ever waiting in the queues at once
+	pub peak: AtomicUsize,
+}
+
+struct Shared {
+	client: usize,
+	policy: LagPolicy,
+	stats: OutboxStats,
+	view: Arc<ClientView>,
+	// Wakes the writer task up to disconnect the client even when its queues are full
+	kick: Notify,
+}
+
+// Queues messages for a client's writer task, critical messages have their own queue which is always emptied first
+#[derive(Clone)]
+pub struct Outbox {
+	critical: mpsc::Sender<Message>,
+	normal: mpsc::Sender<Message>,
+	shared: Arc<Shared>,
+}
+
+pub struct OutboxReceiver {
+	critical: mpsc::Receiver<Message>,
+	normal: mpsc::Receiver<Message>,
+	shared: Arc<Shared>,
+}
+
+pub fn channel(client: usize, size: usize, policy: LagPolicy, view: Arc<ClientView>) -> (Outbox, OutboxReceiver) {
+	let (critical_tx, critical_rx) = mpsc::channel(size);
+	let (normal_tx, normal_rx) = mpsc::channel(size);
+	let shared = Arc::new(Shared {
+		client,
+		policy,
+		stats: OutboxStats::default(),
+		view,
+		kick: Notify::new(),
+	});
+
+	(
+		Outbox {
+			critical: critical_tx,
+			normal: normal_tx,
+			shared: Arc::clone(&shared),
+		},
+		OutboxReceiver {
+			critical: critical_rx,
+			normal: normal_rx,
+			shared,
+		},
+	)
+}
+
+impl Outbox {
+	// Never waits, a client that can't keep up is dealt with according to the lag policy instead
+	pub fn push(&self, msg: Message) {
+		let critical = is_critical(&msg);
+		let queue = if critical { &self.critical } else { &self.normal };
+		match queue.try_send(msg) {
+			Ok(()) => {
+				self.shared.stats.peak.fetch_max(self.len(), Ordering::Relaxed);
+			}
+			// The client is disconnecting
+			Err(TrySendError::Closed(_)) => {}
+			Err(TrySendError::Full(msg)) => {
+				self.shared.stats.dropped.fetch_add(1, Ordering::Relaxed);
+				if matches!(msg, Message::ConnectionRefuse(_)) {
+					self.shared.kick.notify_one();
+				} else {
+					self.lost(critical);
+				}
+			}
+		}
+	}
+
+	// Replies to the client's own messages wait for room instead, which only holds up reading from that client
+	pub async fn reply(&self, msg: Message) {
+		let queue = if is_critical(&msg) { &self.critical } else { &self.normal };
+		// Fails only when the client is disconnecting
+		if queue.send(msg).await.is_ok() {
+			self.shared.stats.peak.fetch_max(self.len(), Ordering::Relaxed);
+		}
+	}
+
+	// Called with the number of broadcasts the connection task skipped after lagging behind
+	pub fn missed(&self, count: u64) {
+		self.shared.stats.missed.fetch_add(count, Ordering::Relaxed);
+		// There's no telling what was in them
+		self.lost(true);
+	}
+
+	fn lost(&self, critical: bool) {
+		match self.shared.policy {
+			LagPolicy::Drop if !critical => {}
+			// Tiles are the bulk of what gets lost
+			LagPolicy::Drop | LagPolicy::Resync => self.resync(),
+			LagPolicy::Kick => self.shared.kick.notify_one(),
+		}
+	}
+
+	// Forgets which sections the client has, so they're sent again as the player moves around
+	pub fn resync(&self) {
+		if self.shared.view.unload_sections() {
+			log_debug!("Resyncing client {}", self.shared.client);
+			self.shared.stats.resyncs.fetch_add(1, Ordering::Relaxed);
+		}
+	}
+
+	// Messages waiting to be written
+	pub fn len(&self) -> usize {
+		self.critical.max_capacity() - self.critical.capacity() + self.normal.max_capacity()
+			- self.normal.capacity()
+	}
+
+	pub fn capacity(&self) -> usize {
+		self.critical.max_capacity() + self.normal.max_capacity()
+	}
+
+	pub fn stats(&self) -> &OutboxStats {
+		&self.shared.stats
+	}
+}
+
+impl OutboxReceiver {
+	// The client's writer task, returns once the client is kicked or every Outbox is gone
+	pub async fn forward<W: AsyncWrite + Unpin>(mut self, mut writer: FramedWriter<W>) -> anyhow::Result<()> {
+		loop {
+			let msg = select! {
+				biased;
+				_ = self.shared.kick.notified() => {
+					log_warn!("Kicking client {}: not keeping up with the server", self.shared.client);
+					let reason = Text::Literal("Kicked for falling too far behind the server".to_owned());
+					let _ = timeout(KICK_TIMEOUT, writer.send(Message::ConnectionRefuse(reason))).await;
+					return Ok(());
+				}
+				Some(msg) = self.critical.recv() => msg,
+				// Both queues close together, once the last Outbox is dropped
+				msg = self.normal.recv() => match msg {
+					Some(msg) => msg,
+					None => return Ok(()),
+				},
+			};
+
+			let kick = matches!(msg, Message::ConnectionRefuse(_));
+			writer.send(msg).await?;
+			self.shared.stats.sent.fetch_add(1, Ordering::Relaxed);
+			if kick {
+				return Ok(());
+			}
+		}
+	}
+}
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{interval, sleep, Instant, MissedTickBehavior};
+
+use crate::binary::types::Text;
+use crate::network::broadcast::Broadcast;
+use crate::network::messages::Message;
+use crate::network::server::Server;
+
+pub const DEFAULT_AUTOSAVE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+pub const DEFAULT_BACKUP_COUNT: usize = 3;
+// How long clients get to receive their kick message before the world is saved and the process exits
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+impl Server {
+	pub async fn save(&self) -> anyhow::Result<()> {
+		// Only one save may touch the world file and its backups at a time
+		let _guard = self.save_lock.lock().await;
+
+		// Cloning is much faster than serializing, so the world is only locked for the copy
+		let snapshot = self.world.read().await.clone();
+		let path = self.world_path.clone();
+		let backups = self.backup_count;
+		tokio::task::spawn_blocking(move || snapshot.save(&path, backups)).await??;
+
+		log_info!("Saved world to {}", self.world_path.display());
+		Ok(())
+	}
+
+	pub async fn run_autosave(self: Arc<Self>) {
+		if self.autosave_interval.is_zero() {
+			return;
+		}
+
+		let mut interval = interval(self.autosave_interval);
+		interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+		// The first tick completes immediately and there is nothing to save yet
+		interval.tick().await;
+
+		loop {
+			interval.tick().await;
+			if let Err(e) = self.save().await {
+				log_error!("Autosave failed: {}", e);
+			}
+		}
+	}
+
+	pub async fn shutdown(&self) -> anyhow::Result<()> {
+		log_info!("Shutting down");
+		// Every client task disconnects after forwarding a ConnectionRefuse
+		let _ = self.broadcast.send(Broadcast::everyone(Message::ConnectionRefuse(Text::Literal(
+			"Server shutting down".to_owned(),
+		))));
+
+		let deadline = Instant::now() + SHUTDOWN_GRACE;
+		while Instant::now() < deadline
+			&& self.clients.lock().await.iter().any(Option::is_some)
+		{
+			sleep(Duration::from_millis(50)).await;
+		}
+
+		self.save().await
+	}
+}
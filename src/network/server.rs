@@ -1,125 +1,307 @@
 use anyhow;
-use rand::random;
-use std::cmp::{max, min};
-use std::pin::Pin;
+use std::cmp::min;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::io::{self, AsyncReadExt};
+use std::time::Duration;
+use tokio::io;
 use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
 use tokio::select;
-use tokio::sync::{broadcast, Mutex, RwLock};
+use tokio::signal;
+use tokio::sync::{broadcast, Mutex, Notify, RwLock};
 
+use crate::binary::reader::Reader;
 use crate::binary::types::{Text, Vector2};
+use crate::config::Config;
+use crate::log::{self, LogLevel};
 use crate::network::transpiled::item_slots;
 use crate::network::messages::{
-	AnglerQuest, ConnectionApprove, DropItem, KillCount, Message, NPCInfo,
-	PillarShieldStrengths, SpawnResponse, WorldTotals, Sanitize, PlayerItemSlot,
+	AnglerQuest, ConnectionApprove, KillCount, Message, MessageDecodeError, NPCInfo,
+	PillarShieldStrengths, SpawnResponse, NetModuleData, WorldTotals, Sanitize, PlayerItemSlot,
+	ChestName, SyncChestItem, SyncChestUser, SyncPlayerChest, UpdateSign, LiquidUpdate,
 };
-use crate::network::client::{Client, ConnectionState, MAX_ITEM_SLOTS};
+use crate::network::client::{Client, ClientView, ConnectionState, MAX_ITEM_SLOTS};
+use crate::network::broadcast::{Broadcast, Scope};
+use crate::network::chat::{chat_message, SERVER_AUTHOR, SERVER_COLOR};
+use crate::network::codec::{FramedReader, FramedWriter};
+use crate::network::commands::CommandRegistry;
+use crate::network::entity::{encode_dummy_npc, entity_item};
+use crate::network::items::{encode_removed_item, DroppedItem, ItemPool, MAX_ITEMS, NEW_ITEM, PLAYER_SIZE};
+use crate::network::outbox::{self, LagPolicy, Outbox, OutboxReceiver};
+use crate::network::tick::TickHook;
 use crate::network::utils::{
-	encode_world_header, get_section_x, get_section_y, get_sections_near,
+	encode_tile_square, encode_world_header, get_section_x, get_section_y, get_sections_near,
 };
-use crate::world::types::World;
+use crate::world::chest::{is_chest, CHEST_NAME_LENGTH};
+use crate::world::entity::EntityInner;
+use crate::world::section::SectionChange;
+use crate::world::sign::is_sign;
+use crate::world::tile::{Liquid, Tile};
+use crate::world::transpiled::tiles::{
+	CONTAINERS, CONTAINERS_2, DISPLAY_DOLL, DRESSERS, FOOD_PLATTER, HAT_RACK, ITEM_FRAME, WEAPONS_RACK_2,
+	WEIGHTED_PRESSURE_PLATE,
+};
+use crate::world::types::{ChestItem as WorldChestItem, World, CHEST_SIZE, ITEM_COUNT};
 
 const GAME_VERSION: &str = "Terraria279";
-const MAX_CLIENTS: usize = 256;
+pub const MAX_CLIENTS: usize = 256;
 const MAX_NAME_LEN: usize = 20;
-const TILE: f32 = 16.;
+pub const TILE: f32 = 16.;
+
+// Squares clients send are only meant to fix up the frames of objects they just placed
+const MAX_CLIENT_SQUARE_SIZE: usize = 10;
+
+// Sends the area's tiles to everyone that has some of it loaded, along with the changes the square carries
+pub fn send_tile_square(
+	tx: &broadcast::Sender<Broadcast>,
+	world: &World,
+	(x, y, width, height): (usize, usize, usize, usize),
+	ignore: Option<usize>,
+	changes: Vec<SectionChange>,
+) -> anyhow::Result<()> {
+	let msg = encode_tile_square(world, x, y, width, height)?;
+	tx.send(Broadcast::new(msg, ignore, Scope::Area(x, y, width, height)).with_changes(changes))?;
+	Ok(())
+}
+
+// Sends every area as a square, the sections changed since the snapshot go along with the last square that covers them
+// Changes outside of the areas don't go anywhere, so those sections get sent again
+pub fn send_tile_squares(
+	tx: &broadcast::Sender<Broadcast>,
+	world: &World,
+	areas: &[(usize, usize, usize, usize)],
+	snapshot: &[Vec<u32>],
+) -> anyhow::Result<()> {
+	let mut last = HashMap::new();
+	for change in world.section_versions.changes_since(snapshot) {
+		let covering = areas.iter().rposition(|&(x, y, width, height)| {
+			(get_section_x(x)..=get_section_x(x + width - 1)).contains(&change.x)
+				&& (get_section_y(y)..=get_section_y(y + height - 1)).contains(&change.y)
+		});
+		if let Some(i) = covering {
+			last.entry(i).or_insert_with(Vec::new).push(change);
+		}
+	}
+
+	for (i, &area) in areas.iter().enumerate() {
+		send_tile_square(tx, world, area, None, last.remove(&i).unwrap_or_default())?;
+	}
+	Ok(())
+}
+
+// Player.tileRangeX and Player.tileRangeY
+const TILE_RANGE_X: i32 = 5;
+const TILE_RANGE_Y: i32 = 4;
+
+// The range around a chest's or sign's top left tile outside of which Player.Update closes it
+fn in_reach(position: &Vector2, x: usize, y: usize) -> bool {
+	let px = ((position.0 + PLAYER_SIZE.0 / 2.) / TILE) as i32;
+	let py = ((position.1 + PLAYER_SIZE.1 / 2.) / TILE) as i32;
+	let (x, y) = (x as i32, y as i32);
+	(x - TILE_RANGE_X..=x + TILE_RANGE_X + 1).contains(&px) && (y - TILE_RANGE_Y..=y + TILE_RANGE_Y + 1).contains(&py)
+}
+
+// Chest.UsingChest
+fn chest_user(clients: &[Option<Client>], chest: i16) -> Option<usize> {
+	clients.iter().position(|c| c.as_ref().is_some_and(|c| c.chest == chest))
+}
+
+// Maps the wire actions of UpdateTile to the wire they act on
+fn wire_of(action: u8) -> u8 {
+	match action {
+		5 | 6 => 1,
+		10 | 11 => 2,
+		12 | 13 => 3,
+		_ => 4,
+	}
+}
 
 pub struct Server {
 	pub world: RwLock<World>,
+	pub world_path: PathBuf,
+	// A zero interval disables autosaving
+	pub autosave_interval: Duration,
+	pub backup_count: usize,
+	pub save_lock: Mutex<()>,
 	pub password: RwLock<String>,
+	pub max_players: usize,
+	// Sent to players once they finish joining
+	pub motd: String,
+	// Size of each player's outbound queues and what happens when they fill up
+	pub queue_size: usize,
+	pub lag_policy: LagPolicy,
 	pub clients: Mutex<[Option<Client>; MAX_CLIENTS]>,
-	pub broadcast: broadcast::Sender<(Message, Option<usize>)>,
+	pub broadcast: broadcast::Sender<Broadcast>,
+	pub tick_hooks: Mutex<Vec<TickHook>>,
+	// Locked after clients and world
+	pub items: Mutex<ItemPool>,
+	pub commands: RwLock<CommandRegistry>,
+	pub bans: Mutex<HashSet<IpAddr>>,
+	// Notified by the exit command
+	pub exit: Notify,
 }
 
 impl Server {
-	pub fn new(world: World, password: &str) -> Server {
+	pub fn new(world: World, world_path: &Path, config: &Config) -> Server {
 		// https://github.com/rust-lang/rust/issues/44796#issuecomment-967747810
 		const INIT_CLIENT_NONE: Option<Client> = None;
 		let (tx, _) = broadcast::channel(1024);
 
 		Server {
 			world: RwLock::new(world),
-			password: RwLock::new(password.to_owned()),
+			world_path: world_path.to_owned(),
+			autosave_interval: config.autosave_interval,
+			backup_count: config.backup_count,
+			save_lock: Mutex::new(()),
+			password: RwLock::new(config.password.clone()),
+			max_players: config.max_players,
+			motd: config.motd.clone(),
+			queue_size: config.queue_size,
+			lag_policy: config.lag_policy,
 			clients: Mutex::new([INIT_CLIENT_NONE; MAX_CLIENTS]),
 			broadcast: tx,
+			tick_hooks: Mutex::new(vec![]),
+			items: Mutex::new(ItemPool::new()),
+			commands: RwLock::new(CommandRegistry::new()),
+			bans: Mutex::new(HashSet::new()),
+			exit: Notify::new(),
 		}
 	}
 
-	pub async fn listen(self, address: &str) -> io::Result<()> {
-		let listener = TcpListener::bind(address).await?;
+	pub async fn listen(self, addresses: &[String]) -> io::Result<()> {
+		let mut listeners = Vec::with_capacity(addresses.len());
+		for address in addresses {
+			listeners.push(TcpListener::bind(address).await?);
+			log_info!("Listening on {}", address);
+		}
+
 		let arc = Arc::new(self);
+		tokio::spawn(Arc::clone(&arc).run_ticks());
+		tokio::spawn(Arc::clone(&arc).run_autosave());
+		for listener in listeners {
+			tokio::spawn(Arc::clone(&arc).accept_connections(listener));
+		}
 
+		tokio::spawn(Arc::clone(&arc).run_console());
+
+		select! {
+			res = signal::ctrl_c() => res?,
+			_ = arc.exit.notified() => {}
+		}
+		if let Err(e) = arc.shutdown().await {
+			log_error!("Failed to save world on shutdown: {}", e);
+		}
+		Ok(())
+	}
+
+	async fn accept_connections(self: Arc<Self>, listener: TcpListener) {
 		loop {
-			let (mut stream, addr) = listener.accept().await?;
-			let rc = Arc::clone(&arc);
-			let rc2 = Arc::clone(&arc);
+			let (mut stream, addr) = match listener.accept().await {
+				Ok(conn) => conn,
+				Err(e) => {
+					log_warn!("Failed to accept connection: {}", e);
+					continue;
+				}
+			};
+			let rc = Arc::clone(&self);
+			let rc2 = Arc::clone(&self);
 
 			tokio::spawn(async move {
-				let src = {
-					let mut clients = rc.clients.lock().await;
-					let Some(id) = clients.iter().position(Option::is_none) else {
-						Message::ConnectionRefuse(Text::Key(
-							"CLI.ServerIsFull".to_owned(),
+				if rc.bans.lock().await.contains(&addr.ip()) {
+					let _ = FramedWriter::new(&mut stream)
+						.send(Message::ConnectionRefuse(Text::Key(
+							"LegacyMultiplayer.3".to_owned(),
 							vec![],
-						))
-						.write_stream(Pin::new(&mut stream))
-						.await
-						.unwrap();
+						)))
+						.await;
+					return;
+				}
+
+				let (src, outbox, queue, view) = {
+					let mut clients = rc.clients.lock().await;
+					let Some(id) = clients.iter().take(rc.max_players).position(Option::is_none) else {
+						let _ = FramedWriter::new(&mut stream)
+							.send(Message::ConnectionRefuse(Text::Key(
+								"CLI.ServerIsFull".to_owned(),
+								vec![],
+							)))
+							.await;
 						return;
 					};
 					let world = rc.world.read().await;
-					clients[id] = Some(Client::new(
-						addr,
-						get_section_x(world.header.width as usize) + 1,
-						get_section_y(world.header.height as usize) + 1,
-					));
-					id
+					let view = Arc::new(ClientView::new(Arc::clone(&world.section_versions)));
+					let (outbox, queue) = outbox::channel(id, rc.queue_size, rc.lag_policy, Arc::clone(&view));
+					clients[id] = Some(Client::new(addr, outbox.clone(), Arc::clone(&view)));
+					(id, outbox, queue, view)
 				};
 
-				let _ = tokio::spawn(async move { rc.accept(&mut stream, src).await }).await;
+				let _ = tokio::spawn(async move { rc.accept(stream, outbox, queue, src, &view).await }).await;
 
 				rc2.clients.lock().await[src] = None;
 			});
 		}
 	}
 
-	async fn accept(&self, stream: &mut TcpStream, src: usize) -> anyhow::Result<()> {
-		let (mut rh, mut wh) = stream.split();
+	async fn accept(
+		&self,
+		stream: TcpStream,
+		outbox: Outbox,
+		queue: OutboxReceiver,
+		src: usize,
+		view: &ClientView,
+	) -> anyhow::Result<()> {
+		let (rh, wh) = stream.into_split();
+		let mut reader = FramedReader::new(rh);
+		// Writing happens on its own task so a slow connection doesn't hold up reading or the broadcasts
+		let mut writer = tokio::spawn(queue.forward(FramedWriter::new(wh)));
 		let mut tx = self.broadcast.clone();
 		let mut rx = self.broadcast.subscribe();
 
 		loop {
-			let mut length = [0u8; 2];
 			select! {
-				read_result = rh.read(&mut length) => {
-					// Player disconnected
-					if read_result.is_err() || read_result.is_ok_and(|l| l == 0) {
-						self.clients.lock().await[src] = None;
-						return Ok(());
-					}
-
-					let length = u16::from_le_bytes(length);
-					if length < 2 {
-						continue;
-					}
-
-					let mut buffer = vec![0u8; length as usize - 2];
-					let read_result = rh.read_exact(&mut buffer).await;
-					if read_result.is_err() || read_result.is_ok_and(|l| l == 0) {
-						self.clients.lock().await[src] = None;
-					} else {
-						let response = self.handle_message(Message::from(buffer), src, &mut tx).await?;
-						for msg in response {
-							msg.write_stream(Pin::new(&mut wh)).await?;
+				msg = reader.next() => {
+					let msg = match msg {
+						Ok(Some(msg)) => msg,
+						// Player disconnected
+						Ok(None) | Err(MessageDecodeError::IO(_)) => {
+							self.clients.lock().await[src] = None;
+							return Ok(());
 						}
+						Err(e) => {
+							// Kick the client instead of trying to make sense of the rest of the stream
+							log_warn!("Kicking client {}: {}", src, e);
+							outbox.push(Message::ConnectionRefuse(Text::Key(
+								"Net.CheatingInvalid".to_owned(),
+								vec![],
+							)));
+							return Ok(());
+						}
+					};
+
+					let response = self.handle_message(msg, src, &mut tx).await?;
+					for msg in response {
+						outbox.reply(msg).await;
 					}
 				}
-				content = rx.recv() => {
-					let (content, ignore_id) = content?;
-					if ignore_id.map_or(true, |id| id != src) {
-						content.write_stream(Pin::new(&mut wh)).await?;
+				content = rx.recv() => match content {
+					Ok(content) => {
+						// The player a change came from already has it
+						if content.ignore == Some(src) {
+							view.acknowledge(&content.changes);
+						} else if view.concerns(&content.scope) {
+							outbox.push(content.msg);
+							view.acknowledge(&content.changes);
+						}
 					}
+					Err(RecvError::Lagged(count)) => outbox.missed(count),
+					Err(RecvError::Closed) => return Ok(()),
+				},
+				// Kicked, or the connection broke
+				res = &mut writer => {
+					self.clients.lock().await[src] = None;
+					return res?;
 				}
 			}
 		}
@@ -129,7 +311,7 @@ impl Server {
 		&self,
 		msg: Message,
 		src: usize,
-		tx: &mut broadcast::Sender<(Message, Option<usize>)>,
+		tx: &mut broadcast::Sender<Broadcast>,
 	) -> anyhow::Result<Vec<Message>> {
 		let mut clients = self.clients.lock().await;
 		let client = clients[src].as_mut().unwrap();
@@ -155,7 +337,7 @@ impl Server {
 						vec![Message::PasswordRequest]
 					}
 				} else {
-					println!("Player tried joining with unsupported version {}", version);
+					log_info!("Player tried joining with unsupported version {}", version);
 					vec![Message::ConnectionRefuse(Text::Key(
 						"LegacyMultiplayer.4".to_owned(),
 						vec![],
@@ -197,8 +379,8 @@ impl Server {
 				}
 
 				let exists_same_name = clients.iter().any(|c_opt| {
-					c_opt.as_ref().map_or(false, |c| {
-						c.details.as_ref().map_or(false, |d| d.name == pd.name)
+					c_opt.as_ref().is_some_and(|c| {
+						c.details.as_ref().is_some_and(|d| d.name == pd.name)
 					})
 				});
 
@@ -226,7 +408,7 @@ impl Server {
 				// TODO: compare client difficulty with world difficulty
 
 				pd.sanitize(src as u8);
-				tx.send((Message::PlayerDetails(pd.clone()), Some(src)))?;
+				tx.send(Broadcast::new(Message::PlayerDetails(pd.clone()), Some(src), Scope::Everyone))?;
 				let c = clients[src].as_mut().unwrap();
 				c.details = Some(pd);
 				c.state = ConnectionState::DetailsReceived;
@@ -234,7 +416,7 @@ impl Server {
 			}
 			Message::PlayerHealth(mut ph) => {
 				ph.sanitize(src as u8);
-				tx.send((Message::PlayerHealth(ph.clone()), Some(src)))?;
+				tx.send(Broadcast::new(Message::PlayerHealth(ph.clone()), Some(src), Scope::Everyone))?;
 				client.health = Some(ph);
 				vec![]
 			}
@@ -246,13 +428,13 @@ impl Server {
 			}
 			Message::PlayerBuffs(mut pb) => {
 				pb.sanitize(src as u8);
-				tx.send((Message::PlayerBuffs(pb.clone()), Some(src)))?;
+				tx.send(Broadcast::new(Message::PlayerBuffs(pb.clone()), Some(src), Scope::Everyone))?;
 				client.buffs = Some(pb);
 				vec![]
 			}
 			Message::PlayerLoadout(mut psl) => {
 				psl.sanitize(src as u8);
-				tx.send((Message::PlayerLoadout(psl.clone()), Some(src)))?;
+				tx.send(Broadcast::new(Message::PlayerLoadout(psl.clone()), Some(src), Scope::Everyone))?;
 				// TODO: maybe handle hide_accessory. still not sure if it matters to the server
 				client.selected_loadout = psl.index;
 				vec![]
@@ -270,7 +452,7 @@ impl Server {
 				}
 
 				if idx < MAX_ITEM_SLOTS {
-					tx.send((Message::PlayerItemSlot(pis.clone()), Some(src)))?;
+					tx.send(Broadcast::new(Message::PlayerItemSlot(pis.clone()), Some(src), Scope::Everyone))?;
 
 					// Don't try to use the client's current loadout slots because it's always sent as 0
 					if !(loadout_start..=loadout_end).contains(&idx) {
@@ -301,8 +483,8 @@ impl Server {
 				// PortalHelper.SyncPortalsOnPlayerJoin(this.whoAmI, 1, dontInclude, out portalSections);
 				// sec_count += portalSections.Count;
 
-				let x_max = get_section_x(w.header.width as usize) - 1;
-				let y_max = get_section_y(w.header.height as usize) - 1;
+				let x_max = get_section_x(w.header.width as usize).saturating_sub(1);
+				let y_max = get_section_y(w.header.height as usize).saturating_sub(1);
 				let (xs, xe, ys, ye) =
 					get_sections_near(w.header.spawn_x, w.header.spawn_y, x_max, y_max);
 				let mut secs = c.encode_sections(&w, xs, xe, ys, ye)?;
@@ -313,7 +495,7 @@ impl Server {
 					&& sr.x <= (w.header.width - 10)
 					&& sr.y >= 10 && sr.y <= (w.header.height - 10)
 				{
-					let (xs, xe, ys, ye) = get_sections_near(sr.x, sr.y, x_max - 1, y_max - 1);
+					let (xs, xe, ys, ye) = get_sections_near(sr.x, sr.y, x_max.saturating_sub(1), y_max.saturating_sub(1));
 					let mut secs = c.encode_sections(&w, xs, xe, ys, ye)?;
 					count += secs.len();
 					res.append(&mut secs);
@@ -338,14 +520,7 @@ impl Server {
 				// for (int index10 = 0; index10 < portalSections.Count; ++index10)
 				//   NetMessage.SendSection(this.whoAmI, portalSections[index10].X, portalSections[index10].Y);
 
-				// for (int number4 = 0; number4 < 400; ++number4)
-				// {
-				// 	if (Main.item[number4].active)
-				// 	{
-				// 		NetMessage.TrySendData(21, this.whoAmI, number: number4);
-				// 		NetMessage.TrySendData(22, this.whoAmI, number: number4);
-				// 	}
-				// }
+				res.append(&mut self.items.lock().await.encode_items());
 
 				// Send all NPCs
 				// todo: use real npc slots
@@ -369,6 +544,15 @@ impl Server {
 					}));
 				}
 
+				// Dummies come after the town NPCs
+				for entity in &w.entities {
+					if let EntityInner::Dummy { npc } = entity.inner {
+						if npc != -1 {
+							res.push(encode_dummy_npc(npc, entity));
+						}
+					}
+				}
+
 				// todo: add actual projectile data
 				// for (int number6 = 0; number6 < 1000; ++number6) {
 				// 	if (Main.projectile[number6].active && (Main.projPet[Main.projectile[number6].type] || Main.projectile[number6].netImportant))
@@ -424,7 +608,7 @@ impl Server {
 					))]);
 				}
 
-				tx.send((Message::PlayerSpawnRequest(psr), Some(src)))?;
+				tx.send(Broadcast::new(Message::PlayerSpawnRequest(psr), Some(src), Scope::Everyone))?;
 				if client.state == ConnectionState::Complete {
 					return Ok(vec![]);
 				}
@@ -435,95 +619,648 @@ impl Server {
 
 				let world = self.world.read().await;
 
-				vec![
+				let mut res = vec![
 					Message::AnglerQuest(AnglerQuest {
 						id: world.header.angler_quest as u8,
 						finished: false,
 					}),
 					Message::PlayerSpawnResponse,
-				]
+				];
+				if !self.motd.is_empty() {
+					res.push(chat_message(SERVER_AUTHOR, Text::Literal(self.motd.clone()), SERVER_COLOR)?);
+				}
+				res
 			}
 			// This message just gets broadcasted
 			Message::PlayerPickTile(mut ppt) => {
 				ppt.sanitize(src as u8);
-				tx.send((Message::PlayerPickTile(ppt), Some(src)))?;
+				let scope = Scope::Tile(ppt.x as usize, ppt.y as usize);
+				tx.send(Broadcast::new(Message::PlayerPickTile(ppt), Some(src), scope))?;
 				vec![]
 			}
-			Message::UpdateTile(ppt) => {
-				if ppt.action == 0 && ppt.target_type == 0 {
-					let world = self.world.read().await;
-					let tile = &world.tiles[ppt.x as usize][ppt.y as usize];
-					let i = tile.get_dropped_item_tile(client, &world, &mut 0, &mut 0, &mut 0);
-					if i > 0 {
-						tx.send((
-							Message::DropItem(DropItem {
-								id: 0,
-								position: Vector2(ppt.x as f32 * TILE, ppt.y as f32 * TILE),
-								velocity: Vector2(random::<f32>() * 6. - 3., (random::<f32>() * -2.5) - 1.5),
-								item_id: i,
-								own_ignore: false,
-								prefix: 0,
-								stack: 1,
+			Message::UpdateTile(ut) => {
+				let mut world = self.world.write().await;
+				if !world.in_world(ut.x as i32, ut.y as i32, 3) {
+					return Ok(vec![]);
+				}
+
+				let x = ut.x as usize;
+				let y = ut.y as usize;
+				let mut snapshot = world.section_versions.snapshot();
+				// Clients can't edit tiles in sections they haven't been sent yet
+				let loaded = client.has_section_loaded(x, y);
+				// For the kill actions, target_type is 1 when the tile was only damaged, which changes nothing
+				if loaded && matches!(ut.action, 0 | 2 | 4) && ut.target_type == 1 {
+					return Ok(vec![]);
+				}
+
+				let changed = loaded
+					&& match ut.action {
+						// Chests go through PlaceChest so their contents can be checked
+						0 | 4 if !is_chest(&world.tiles[x][y]) => {
+							// Tiles holding up trees and altars can't be broken, the client gets the real tile back
+							let Some(cascade) = world.kill_object(x, y) else {
+								return Ok(vec![encode_tile_square(&world, x, y, 1, 1)?]);
+							};
+
+							if ut.action == 0 {
+								let mut drops = vec![];
+								for (i, j, tile) in &cascade.objects {
+									let position = Vector2(*i as f32 * TILE, *j as f32 * TILE);
+									for (id, stack) in tile.get_dropped_items_tile(client, &world, *i, *j) {
+										drops.push(DroppedItem::with_random_velocity(id, stack, 0, position.clone()));
+									}
+								}
+								for item in drops {
+									self.spawn_item(clients.as_slice(), item, None).await;
+								}
+							}
+
+							for entity in cascade.entities {
+								self.remove_entity(clients.as_mut_slice(), entity).await;
+							}
+
+							// Clients only remove the tile they were told about, the rest of the cascade is sent as a square
+							let (i, j, width, height) = cascade.area;
+							world.frame_area(i, j, width, height);
+							if width > 1 || height > 1 {
+								let changes = world.section_versions.changes_since(&snapshot);
+								send_tile_square(tx, &world, cascade.area, None, changes)?;
+								snapshot = world.section_versions.snapshot();
+							}
+							true
+						}
+						1 if is_sign(ut.target_type) => world.place_sign(x, y, ut.target_type, ut.target_style).is_some(),
+						1 => world.place_tile(x, y, ut.target_type, ut.target_style),
+						// Walls that can't be broken yet fall through, so the client gets the wall back in a square
+						2 if world.can_kill_wall(x, y) => {
+							// Walls without an entry in KillWall_GetItemDrops drop nothing
+							let i = world.tiles[x][y].get_dropped_item_wall();
+							if world.kill_wall(x, y) && i > 0 {
+								let item = DroppedItem::with_random_velocity(i, 1, 0, Vector2(x as f32 * TILE, y as f32 * TILE));
+								self.spawn_item(clients.as_slice(), item, None).await;
+							}
+							true
+						}
+						3 => world.place_wall(x, y, ut.target_type as u16),
+						5 | 10 | 12 | 16 => world.set_wire(x, y, wire_of(ut.action), true),
+						6 | 11 | 13 | 17 => world.set_wire(x, y, wire_of(ut.action), false),
+						7 => world.pound_tile(x, y),
+						8 => world.set_actuator(x, y, true),
+						9 => world.set_actuator(x, y, false),
+						14 => world.slope_tile(x, y, ut.target_type as u8),
+						// Track framing, logic gates and actuation are just relayed for now
+						15 | 18 | 19 | 20 => true,
+						21 => world.replace_tile(x, y, ut.target_type, ut.target_style),
+						22 => world.replace_wall(x, y, ut.target_type as u16),
+						23 => world.slope_tile(x, y, ut.target_type as u8) | world.pound_tile(x, y),
+						_ => false,
+					};
+				// Other clients only hear about edits that went through, the sender gets the real tile back
+				if !changed {
+					return Ok(vec![encode_tile_square(&world, x, y, 1, 1)?]);
+				}
+				world.frame_area(x, y, 1, 1);
+
+				let changes = world.section_versions.changes_since(&snapshot);
+				tx.send(Broadcast::new(Message::UpdateTile(ut), Some(src), Scope::Tile(x, y)).with_changes(changes))?;
+				vec![]
+			}
+			Message::ToggleSwitch(ts) => {
+				let mut world = self.world.write().await;
+				if !world.in_world(ts.x as i32, ts.y as i32, 1) {
+					return Ok(vec![]);
+				}
+				let (x, y) = (ts.x as usize, ts.y as usize);
+				// Weighted plates are pressed by the server itself, from where the players stand
+				let tile = &world.tiles[x][y];
+				if !client.has_section_loaded(x, y) || (tile.active && tile.id == WEIGHTED_PRESSURE_PLATE) {
+					return Ok(vec![]);
+				}
+
+				// Other clients flip the switch themselves, what its signal does is sent as it happens
+				let snapshot = world.section_versions.snapshot();
+				if world.hit_switch(x, y).is_some() {
+					let changes = world.section_versions.changes_since(&snapshot);
+					tx.send(Broadcast::new(Message::ToggleSwitch(ts), Some(src), Scope::Tile(x, y)).with_changes(changes))?;
+				}
+				vec![]
+			}
+			Message::TileSquare(ts) => {
+				let mut world = self.world.write().await;
+				let (x, y) = (ts.x as usize, ts.y as usize);
+				let (width, height) = (ts.width as usize, ts.height as usize);
+				if ts.x < 0
+					|| ts.y < 0
+					|| !(1..=MAX_CLIENT_SQUARE_SIZE).contains(&width)
+					|| !(1..=MAX_CLIENT_SQUARE_SIZE).contains(&height)
+					|| !world.in_world((x + width) as i32, (y + height) as i32, 1)
+				{
+					return Ok(vec![]);
+				}
+				// Like UpdateTile, every corner has to be in a section the client was sent
+				let corners = [(x, y), (x + width - 1, y), (x, y + height - 1), (x + width - 1, y + height - 1)];
+				if !corners.iter().all(|(i, j)| client.has_section_loaded(*i, *j)) {
+					return Ok(vec![]);
+				}
+
+				let mut r = Reader::new(&ts.tiles);
+				let tiles: Result<Vec<Tile>, _> = (0..width * height).map(|_| Tile::decode_square(&mut r)).collect();
+				// The client's version gets replaced with ours when the square doesn't make sense
+				let Some(tiles) = tiles.ok().filter(|_| r.cur == r.buf.len()) else {
+					return Ok(vec![encode_tile_square(&world, x, y, width, height)?]);
+				};
+				let snapshot = world.section_versions.snapshot();
+				if !world.place_tile_square(x, y, width, height, tiles) {
+					return Ok(vec![encode_tile_square(&world, x, y, width, height)?]);
+				}
+
+				world.frame_area(x, y, width, height);
+				let changes = world.section_versions.changes_since(&snapshot);
+				send_tile_square(tx, &world, (x, y, width, height), Some(src), changes)?;
+				vec![]
+			}
+			Message::RequestChestOpen(rco) => {
+				let world = self.world.read().await;
+				let (x, y) = (rco.x as usize, rco.y as usize);
+				if !world.in_world(rco.x as i32, rco.y as i32, 1) || !client.has_section_loaded(x, y) {
+					return Ok(vec![]);
+				}
+				let Some(id) = world.chest_origin(x, y).and_then(|(x, y)| world.find_chest(x, y)) else {
+					return Ok(vec![]);
+				};
+
+				let chest = world.chests[id].as_ref().unwrap();
+				if !in_reach(&client.view.position(), chest.x as usize, chest.y as usize) {
+					return Ok(vec![]);
+				}
+
+				// Only one player can have a chest open at a time
+				if chest_user(&clients[..], id as i16).is_some_and(|user| user != src) {
+					return Ok(vec![]);
+				}
+
+				let mut res: Vec<Message> = chest
+					.items
+					.iter()
+					.enumerate()
+					.map(|(slot, item)| {
+						Message::SyncChestItem(SyncChestItem {
+							chest: id as i16,
+							slot: slot as u8,
+							stack: item.stack,
+							prefix: item.prefix,
+							item_id: item.id as i16,
+						})
+					})
+					.collect();
+				res.push(Message::SyncPlayerChest(SyncPlayerChest {
+					chest: id as i16,
+					x: chest.x as i16,
+					y: chest.y as i16,
+					name_length: 0,
+					name: None,
+				}));
+
+				clients[src].as_mut().unwrap().chest = id as i16;
+				tx.send(Broadcast::new(
+					Message::SyncChestUser(SyncChestUser {
+						client_id: src as u8,
+						chest: id as i16,
+					}),
+					Some(src),
+					Scope::Everyone,
+				))?;
+				res
+			}
+			Message::SyncChestItem(sci) => {
+				// Players can only change the chest they have open
+				if sci.chest < 0
+					|| sci.chest != client.chest
+					|| sci.slot as i16 >= CHEST_SIZE
+					|| !(0..ITEM_COUNT).contains(&sci.item_id)
+					|| sci.stack < 0
+				{
+					return Ok(vec![]);
+				}
+
+				let mut world = self.world.write().await;
+				if let Some(chest) = world.chests.get_mut(sci.chest as usize).and_then(Option::as_mut) {
+					chest.items[sci.slot as usize] = if sci.stack == 0 || sci.item_id == 0 {
+						WorldChestItem::default()
+					} else {
+						WorldChestItem {
+							id: sci.item_id as i32,
+							stack: sci.stack,
+							prefix: sci.prefix,
+						}
+					};
+				}
+				vec![]
+			}
+			Message::SyncPlayerChest(spc) => {
+				// A name length other than 0 renames the chest the player had open
+				if spc.name_length != 0 && client.chest >= 0 {
+					let name: String = if spc.name_length == u8::MAX {
+						String::new()
+					} else {
+						spc.name.unwrap_or_default().chars().take(CHEST_NAME_LENGTH).collect()
+					};
+
+					let mut world = self.world.write().await;
+					if let Some(chest) = world.chests.get_mut(client.chest as usize).and_then(Option::as_mut) {
+						chest.name = name.clone();
+						let scope = Scope::Tile(chest.x as usize, chest.y as usize);
+						tx.send(Broadcast::new(
+							Message::ChestName(ChestName {
+								chest: client.chest,
+								x: chest.x as i16,
+								y: chest.y as i16,
+								name,
 							}),
-							None,
+							Some(src),
+							scope,
 						))?;
 					}
 				}
-				tx.send((Message::UpdateTile(ppt), Some(src)))?;
+
+				// -1 closes the chest, other negative ids are the player's own banks
+				if spc.chest < 0 || spc.chest == client.chest {
+					client.chest = spc.chest;
+					tx.send(Broadcast::new(
+						Message::SyncChestUser(SyncChestUser {
+							client_id: src as u8,
+							chest: spc.chest,
+						}),
+						Some(src),
+						Scope::Everyone,
+					))?;
+				}
+				vec![]
+			}
+			Message::PlaceChest(mut pc) => {
+				let id = match pc.action {
+					0 | 1 => CONTAINERS,
+					2 | 3 => DRESSERS,
+					4 | 5 => CONTAINERS_2,
+					_ => return Ok(vec![]),
+				};
+
+				let mut world = self.world.write().await;
+				let (x, y) = (pc.x as usize, pc.y as usize);
+				let position = client.view.position();
+				let reachable = world.in_world(pc.x as i32, pc.y as i32, 1)
+					&& client.has_section_loaded(x, y)
+					&& in_reach(&position, x, y);
+				let snapshot = world.section_versions.snapshot();
+				if pc.action % 2 == 0 {
+					if let Some(chest) = reachable.then(|| world.place_chest(x, y, id, pc.style)).flatten() {
+						pc.chest = chest as i16;
+						let changes = world.section_versions.changes_since(&snapshot);
+						tx.send(Broadcast::new(Message::PlaceChest(pc), None, Scope::Tile(x, y)).with_changes(changes))?;
+						return Ok(vec![]);
+					}
+
+					// The client placed the chest on its side already and used up the item, which it gets back
+					let width = if id == DRESSERS { 54 } else { 36 };
+					let chest = Tile {
+						active: true,
+						id,
+						frame_x: pc.style.max(0) * width,
+						..Default::default()
+					};
+					let position = Vector2(position.0 + PLAYER_SIZE.0 / 2., position.1 + PLAYER_SIZE.1 / 2.);
+					let drops = chest.get_dropped_items_tile(client, &world, x, y);
+					for (item, stack) in drops {
+						let item = DroppedItem::with_random_velocity(item, stack, 0, position.clone());
+						self.spawn_item(clients.as_slice(), item, None).await;
+					}
+
+					// A chest id of -1 makes the client remove it again
+					pc.chest = -1;
+					vec![Message::PlaceChest(pc)]
+				} else {
+					let tile = &world.tiles[x][y];
+					if !reachable || !tile.active || tile.id != id {
+						return Ok(vec![]);
+					}
+					let Some((ox, oy)) = world.chest_origin(x, y) else {
+						return Ok(vec![]);
+					};
+					// The drops are worked out before the chest is gone
+					let drops = world.tiles[ox][oy].clone().get_dropped_items_tile(client, &world, ox, oy);
+					let open = world.find_chest(ox, oy).is_some_and(|chest| chest_user(&clients[..], chest as i16).is_some());
+					if open {
+						return Ok(vec![]);
+					}
+					let Some(chest) = world.kill_chest(x, y) else {
+						return Ok(vec![]);
+					};
+					let position = Vector2(ox as f32 * TILE, oy as f32 * TILE);
+					for (item, stack) in drops {
+						let item = DroppedItem::with_random_velocity(item, stack, 0, position.clone());
+						self.spawn_item(clients.as_slice(), item, None).await;
+					}
+
+					pc.chest = chest as i16;
+					pc.style = 0;
+					let changes = world.section_versions.changes_since(&snapshot);
+					tx.send(Broadcast::new(Message::PlaceChest(pc), None, Scope::Tile(x, y)).with_changes(changes))?;
+					vec![]
+				}
+			}
+			Message::ReadSign(rs) => {
+				let mut world = self.world.write().await;
+				let (x, y) = (rs.x as usize, rs.y as usize);
+				if !world.in_world(rs.x as i32, rs.y as i32, 1) || !client.has_section_loaded(x, y) {
+					return Ok(vec![]);
+				}
+				let position = client.view.position();
+				let Some((x, y)) = world.sign_origin(x, y).filter(|&(x, y)| in_reach(&position, x, y)) else {
+					return Ok(vec![]);
+				};
+				let Some(id) = world.read_sign(x, y) else {
+					return Ok(vec![]);
+				};
+
+				let sign = world.signs[id].as_ref().unwrap();
+				vec![Message::UpdateSign(UpdateSign {
+					sign: id as i16,
+					x: sign.x as i16,
+					y: sign.y as i16,
+					text: sign.text.clone(),
+					client_id: src as u8,
+					flags: 0,
+				})]
+			}
+			Message::UpdateSign(mut us) => {
+				let mut world = self.world.write().await;
+				let (x, y) = (us.x as usize, us.y as usize);
+				if !world.in_world(us.x as i32, us.y as i32, 1) || !client.has_section_loaded(x, y) {
+					return Ok(vec![]);
+				}
+				// Look the sign up by position, the id the client sent can't be trusted
+				let position = client.view.position();
+				let Some((x, y)) = world.sign_origin(x, y).filter(|&(x, y)| in_reach(&position, x, y)) else {
+					return Ok(vec![]);
+				};
+				let Some(id) = world.read_sign(x, y) else {
+					return Ok(vec![]);
+				};
+
+				let sign = world.signs[id].as_mut().unwrap();
+				if sign.text == us.text {
+					return Ok(vec![]);
+				}
+				sign.text = us.text.clone();
+
+				us.sign = id as i16;
+				us.x = sign.x as i16;
+				us.y = sign.y as i16;
+				us.client_id = src as u8;
+				let scope = Scope::Tile(sign.x as usize, sign.y as usize);
+				tx.send(Broadcast::new(Message::UpdateSign(us), Some(src), scope))?;
+				vec![]
+			}
+			// Only dummies are simulated by the server, they can't be hurt so hits only show their damage
+			Message::StrikeNPC(sn) => {
+				let world = self.world.read().await;
+				let Some(i) = world.find_dummy(sn.npc).filter(|_| sn.damage >= 0) else {
+					return Ok(vec![]);
+				};
+				let (x, y) = (world.entities[i].x as usize, world.entities[i].y as usize);
+				if !client.has_section_loaded(x, y) {
+					return Ok(vec![]);
+				}
+
+				tx.send(Broadcast::new(Message::StrikeNPC(sn), Some(src), Scope::Tile(x, y)))?;
+				vec![]
+			}
+			Message::PlaceTileEntity(pte) => {
+				let mut world = self.world.write().await;
+				if !world.in_world(pte.x as i32, pte.y as i32, 1) || !client.has_section_loaded(pte.x as usize, pte.y as usize) {
+					return Ok(vec![]);
+				}
+
+				if let Some(i) = world.place_entity(pte.x as usize, pte.y as usize, pte.kind) {
+					self.sync_entity(&world.entities[i]);
+				}
+				vec![]
+			}
+			Message::PlaceItemFrameItem(p) => {
+				let item = (p.item_id, p.stack, p.prefix);
+				self.place_entity_item(clients.as_slice(), src, (p.x, p.y), ITEM_FRAME, item).await;
+				vec![]
+			}
+			Message::PlaceWeaponsRackItem(p) => {
+				let item = (p.item_id, p.stack, p.prefix);
+				self.place_entity_item(clients.as_slice(), src, (p.x, p.y), WEAPONS_RACK_2, item).await;
+				vec![]
+			}
+			Message::PlaceFoodPlatterItem(p) => {
+				let item = (p.item_id, p.stack, p.prefix);
+				self.place_entity_item(clients.as_slice(), src, (p.x, p.y), FOOD_PLATTER, item).await;
+				vec![]
+			}
+			Message::TileEntityInteraction(mut tei) => {
+				tei.sanitize(src as u8);
+				// -1 is the player walking away
+				if tei.entity != -1 {
+					let world = self.world.read().await;
+					let Some(i) = world.find_entity_by_id(tei.entity) else {
+						return Ok(vec![]);
+					};
+					let entity = &world.entities[i];
+					if !client.has_section_loaded(entity.x as usize, entity.y as usize) {
+						return Ok(vec![]);
+					}
+					// Only one player can use an entity at a time
+					let used = clients
+						.iter()
+						.enumerate()
+						.any(|(i, c)| i != src && c.as_ref().is_some_and(|c| c.entity == tei.entity));
+					if used {
+						return Ok(vec![]);
+					}
+				}
+
+				clients[src].as_mut().unwrap().entity = tei.entity;
+				// The player's own client only opens the interface once it gets this back
+				tx.send(Broadcast::everyone(Message::TileEntityInteraction(tei)))?;
+				vec![]
+			}
+			// Players can only change the doll or rack they have open
+			Message::DisplayDollItem(mut ddi) => {
+				ddi.sanitize(src as u8);
+				let Some(item) = entity_item(ddi.item_id, ddi.stack, ddi.prefix).filter(|_| client.entity == ddi.entity) else {
+					return Ok(vec![]);
+				};
+
+				let mut world = self.world.write().await;
+				if let Some((x, y)) = world.set_entity_slot(ddi.entity, DISPLAY_DOLL, ddi.slot as usize, item) {
+					tx.send(Broadcast::new(Message::DisplayDollItem(ddi), Some(src), Scope::Tile(x, y)))?;
+				}
+				vec![]
+			}
+			Message::HatRackItem(mut hri) => {
+				hri.sanitize(src as u8);
+				let Some(item) = entity_item(hri.item_id, hri.stack, hri.prefix).filter(|_| client.entity == hri.entity) else {
+					return Ok(vec![]);
+				};
+
+				let mut world = self.world.write().await;
+				if let Some((x, y)) = world.set_entity_slot(hri.entity, HAT_RACK, hri.slot as usize, item) {
+					tx.send(Broadcast::new(Message::HatRackItem(hri), Some(src), Scope::Tile(x, y)))?;
+				}
+				vec![]
+			}
+			// Buckets and sponges, the change reaches everyone with the next liquid update
+			Message::LiquidUpdate(lu) => {
+				let mut world = self.world.write().await;
+				if !world.in_world(lu.x as i32, lu.y as i32, 1) {
+					return Ok(vec![]);
+				}
+				let (x, y) = (lu.x as usize, lu.y as usize);
+
+				let valid = client.has_section_loaded(x, y)
+					&& Liquid::from_id(lu.liquid_kind).is_some_and(|kind| world.set_liquid(x, y, lu.liquid, kind));
+				if valid {
+					return Ok(vec![]);
+				}
+				// Put the client's tile back the way the server has it
+				let tile = &world.tiles[x][y];
+				vec![Message::LiquidUpdate(LiquidUpdate {
+					x: lu.x,
+					y: lu.y,
+					liquid: tile.liquid,
+					liquid_kind: tile.liquid_kind.id(),
+				})]
+			}
+			Message::DropItem(di) if di.id == NEW_ITEM => {
+				if !(1..ITEM_COUNT).contains(&di.item_id) || di.stack <= 0 {
+					return Ok(vec![]);
+				}
+
+				let item = DroppedItem::new(di.item_id, di.stack, di.prefix, di.position, di.velocity);
+				// own_ignore is set when the item was dropped on purpose, like vanilla's noDelay
+				let own_ignore = (!di.own_ignore).then_some(src);
+				self.spawn_item(clients.as_slice(), item, own_ignore).await;
+				vec![]
+			}
+			// Owners send these when they pick an item up or when it moves
+			Message::DropItem(di) if (0..MAX_ITEMS as i16).contains(&di.id) => {
+				let id = di.id as usize;
+				let mut items = self.items.lock().await;
+				let Some(item) = items.get_mut(id) else {
+					return Ok(if di.item_id == 0 { vec![] } else { vec![encode_removed_item(id)] });
+				};
+				// Only the owner may touch the item, everyone else gets corrected
+				if item.owner as usize != src {
+					return Ok(vec![item.encode(id)]);
+				}
+
+				if di.item_id == 0 || di.stack <= 0 {
+					items.remove(id);
+					tx.send(Broadcast::new(encode_removed_item(id), Some(src), Scope::Everyone))?;
+					return Ok(vec![]);
+				}
+				// Picking part of a stack up can only make it smaller
+				item.stack = min(item.stack, di.stack);
+				item.position = di.position;
+				item.velocity = di.velocity;
+				tx.send(Broadcast::new(item.encode(id), Some(src), Scope::Everyone))?;
 				vec![]
 			}
-			Message::PlayerReserveItem(mut pri) => {
-				pri.sanitize(src as u8);
-				tx.send((Message::PlayerReserveItem(pri), None))?;
+			// Clients only send this to give up an item after DereserveItem
+			Message::PlayerReserveItem(pri) => {
+				if let Some(msg) = self.items.lock().await.release(pri.id as usize, src) {
+					tx.send(Broadcast::everyone(msg))?;
+				}
 				vec![]
 			}
 			Message::PlayerAction(mut pa) => {
-				if pa.selected_item != client.selected_item {
+				if pa.selected_item != client.selected_item && log::enabled(LogLevel::Debug) {
 					dbg!(client.items.iter().skip(item_slots::ARMOR_LOADOUT_0_START).take(3).collect::<Vec<&Option<PlayerItemSlot>>>());
 					dbg!(client.items.iter().skip(item_slots::ARMOR_LOADOUT_1_START).take(3).collect::<Vec<&Option<PlayerItemSlot>>>());
 					dbg!(client.items.iter().skip(item_slots::ARMOR_LOADOUT_2_START).take(3).collect::<Vec<&Option<PlayerItemSlot>>>());
 				}
 				client.selected_item = pa.selected_item;
+				client.view.set_position(&pa.position);
 				pa.sanitize(src as u8);
 
 				let w = self.world.read().await;
-				let c = client;
-
-				let x_max = get_section_x(w.header.width as usize) - 1;
-				let y_max = get_section_y(w.header.height as usize) - 1;
+				let position = pa.position.clone();
+				tx.send(Broadcast::new(Message::PlayerAction(pa), Some(src), Scope::Near(position.clone())))?;
 
-				let x = get_section_x((pa.position.0 / TILE) as usize);
-				let y = get_section_y((pa.position.1 / TILE) as usize);
-
-				let xs = max(x - 1, 0);
-				let xe = min(x + 1, x_max);
-				let ys = max(y - 1, 0);
-				let ye = min(y + 1, y_max);
-				tx.send((Message::PlayerAction(pa), Some(src)))?;
-
-				let sec = c.encode_sections(&w, xs, xe, ys, ye)?;
-				if sec.is_empty() {
-					vec![]
-				} else {
-					sec
-				}
+				// Sends sections the player is coming near, and ones that changed since they were last sent
+				client.encode_sections_near(&w, &position)?
 			}
 			// Just gets broadcasted
 			Message::PlayInstrument(mut pi) => {
 				pi.sanitize(src as u8);
-				tx.send((Message::PlayInstrument(pi), Some(src)))?;
+				let scope = Scope::Near(client.view.position());
+				tx.send(Broadcast::new(Message::PlayInstrument(pi), Some(src), scope))?;
 				vec![]
 			}
+			Message::NetModule(nm) => {
+				let data = match NetModuleData::decode(nm.module, &nm.payload) {
+					Ok(Some(data)) => data,
+					Ok(None) => {
+						log_debug!("Not yet implemented net module {}: {:?}", nm.module, nm.payload);
+						return Ok(vec![]);
+					}
+					Err(e) => {
+						log_warn!("Kicking client {}: malformed net module {}: {}", src, nm.module, e);
+						return Ok(vec![Message::ConnectionRefuse(Text::Key(
+							"Net.CheatingInvalid".to_owned(),
+							vec![],
+						))]);
+					}
+				};
+
+				match data {
+					NetModuleData::ChatRequest { command, text } => {
+						if client.state != ConnectionState::Complete {
+							return Ok(vec![]);
+						}
+						let name = client.details.as_ref().map_or(String::new(), |d| d.name.clone());
+						drop(clients);
+						self.handle_chat(src, &name, &command, &text).await?
+					}
+					_ => vec![],
+				}
+			}
 			// Server does nothing
 			Message::InventorySynced => vec![],
 			Message::Custom(code, buf) => {
-				println!("Custom ({}): {:?}", code, buf);
+				log_debug!("Custom ({}): {:?}", code, buf);
 				vec![]
 			}
 			pkt => {
-				println!("Not yet implemented packet: {:?}", pkt);
+				log_debug!("Not yet implemented packet: {:?}", pkt);
 				vec![]
 			}
 		})
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// Where a player stands with their centre on the given tile
+	fn standing_at(x: i32, y: i32) -> Vector2 {
+		Vector2(x as f32 * TILE - PLAYER_SIZE.0 / 2. + 8., y as f32 * TILE - PLAYER_SIZE.1 / 2. + 8.)
+	}
+
+	#[test]
+	fn reach_spans_the_tile_range_around_the_origin() {
+		// The range is one tile wider to the right and below since objects are at least 2x2
+		for (x, y) in [(95, 96), (106, 96), (100, 105), (106, 105)] {
+			assert!(in_reach(&standing_at(x, y), 100, 100), "{x}, {y}");
+		}
+		for (x, y) in [(94, 100), (107, 100), (100, 95), (100, 106)] {
+			assert!(!in_reach(&standing_at(x, y), 100, 100), "{x}, {y}");
+		}
+	}
+}
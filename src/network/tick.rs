@@ -0,0 +1,80 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::{interval, MissedTickBehavior};
+
+use crate::network::broadcast::Broadcast;
+use crate::network::messages::{Message, UpdateTime};
+use crate::network::server::Server;
+use crate::network::utils::encode_world_header;
+use crate::world::types::World;
+
+pub const TICKS_PER_SECOND: u32 = 60;
+// Clients run their own clock, so they only need to be corrected every now and then
+const TIME_SYNC_INTERVAL: u64 = 360;
+// Main.UpdateServer resends the world header once a minute
+const WORLD_HEADER_INTERVAL: u64 = 3600;
+// Item.keepTime, how long a released item stays unowned
+const ITEM_OWNER_INTERVAL: u64 = 15;
+// Liquids move at most one cell per update
+const LIQUID_INTERVAL: u64 = 5;
+
+// Work other subsystems run once per tick, with the world locked for writing
+// Gets the current tick and the broadcast channel for anything that has to reach the clients
+pub type TickHook =
+	Box<dyn FnMut(&mut World, u64, &broadcast::Sender<Broadcast>) + Send + Sync>;
+
+impl Server {
+	#[allow(dead_code)]
+	pub async fn on_tick(&self, hook: TickHook) {
+		self.tick_hooks.lock().await.push(hook);
+	}
+
+	pub async fn run_ticks(self: Arc<Self>) {
+		let mut interval = interval(Duration::from_secs(1) / TICKS_PER_SECOND);
+		// Falling behind shouldn't make the world run faster afterwards
+		interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+		let mut tick = 0;
+		loop {
+			interval.tick().await;
+			self.tick(tick).await;
+			tick += 1;
+		}
+	}
+
+	async fn tick(&self, tick: u64) {
+		if tick.is_multiple_of(ITEM_OWNER_INTERVAL) {
+			self.update_item_owners().await;
+		}
+
+		// Taken before the world, like everywhere else
+		let players = Self::player_hitboxes(self.clients.lock().await.as_slice());
+		let mut hooks = self.tick_hooks.lock().await;
+		let mut world = self.world.write().await;
+
+		// Sending fails when nobody is connected, which is fine
+		if world.update_time() || tick.is_multiple_of(WORLD_HEADER_INTERVAL) {
+			let _ = self.broadcast.send(Broadcast::everyone(encode_world_header(&world.header)));
+		} else if tick.is_multiple_of(TIME_SYNC_INTERVAL) {
+			let _ = self.broadcast.send(Broadcast::everyone(Message::UpdateTime(UpdateTime {
+				day_time: world.header.day_time,
+				time: world.header.time as i32,
+				sun_mod_y: 0,
+				moon_mod_y: 0,
+			})));
+		}
+
+		if tick.is_multiple_of(LIQUID_INTERVAL) {
+			let snapshot = world.section_versions.snapshot();
+			let changes = world.update_liquids();
+			self.sync_liquids(&mut world, changes, &snapshot);
+		}
+		self.run_dummies(&mut world, &players);
+		self.run_wiring(&mut world, &players);
+
+		for hook in hooks.iter_mut() {
+			hook(&mut world, tick, &self.broadcast);
+		}
+	}
+}
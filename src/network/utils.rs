@@ -1,16 +1,16 @@
-use std::cmp::{max, min};
+use std::cmp::min;
 use std::io::{self, BufWriter};
 
 use crate::binary::writer::Writer;
-use crate::network::messages::{Message, WorldHeader};
+use crate::network::messages::{Message, TileSquare, UpdateTileEntity, WorldHeader};
+use crate::world::entity::Entity;
 use crate::world::tile::Tile;
 use crate::world::transpiled::tile_flags::ALLOWS_SAVE_COMPRESSION_BATCHING;
 use crate::world::types::{Header, World};
 use flate2::write::ZlibEncoder;
 use flate2::{Compress, Compression};
-use num_traits::Num;
-use rand::distributions::{Distribution, Standard};
-use rand::random;
+use rand::distributions::uniform::SampleUniform;
+use rand::{thread_rng, Rng};
 
 use crate::world::transpiled::tiles::*;
 
@@ -28,9 +28,8 @@ pub fn flags(a: bool, b: bool, c: bool, d: bool, e: bool, f: bool, g: bool, h: b
 }
 
 // random in range (inclusive) for int types
-pub fn rr<T: Num + From<u8> + Clone>(lower: T, upper: T) -> T
-where Standard: Distribution<T> {
-	random::<T>() % (upper - lower.clone() + T::from(1u8)) + lower
+pub fn rr<T: SampleUniform + PartialOrd>(lower: T, upper: T) -> T {
+	thread_rng().gen_range(lower..=upper)
 }
 
 const SECTION_WIDTH: usize = 200;
@@ -51,9 +50,9 @@ pub fn get_sections_near(
 	max_sec_y: usize,
 ) -> (usize, usize, usize, usize) {
 	// these offsets are the value the are the values that the game uses. dont ask me
-	let sec_x_start = max(get_section_x(x as usize) - 2, 0);
+	let sec_x_start = min(get_section_x(x.max(0) as usize).saturating_sub(2), max_sec_x);
 	let sec_x_end = min(sec_x_start + 4, max_sec_x);
-	let sec_y_start = max(get_section_y(y as usize) - 1, 0);
+	let sec_y_start = min(get_section_y(y.max(0) as usize).saturating_sub(1), max_sec_y);
 	let sec_y_end = min(sec_y_start + 2, max_sec_y);
 
 	(sec_x_start, sec_x_end, sec_y_start, sec_y_end)
@@ -87,8 +86,7 @@ pub fn encode_tiles(world: &World, sec_x: usize, sec_y: usize) -> io::Result<Mes
 			let tile = &world.tiles[x][y];
 
 			if !(x == x_start && y == y_start) {
-				// todo: ensure isTheSameAs is like PartialEq
-				if tile == last_tile && ALLOWS_SAVE_COMPRESSION_BATCHING[tile.id as usize] {
+				if tile == last_tile && (!tile.active || ALLOWS_SAVE_COMPRESSION_BATCHING[tile.id as usize]) {
 					repeat_count += 1;
 					continue;
 				}
@@ -139,50 +137,86 @@ pub fn encode_tiles(world: &World, sec_x: usize, sec_y: usize) -> io::Result<Mes
 
 	last_tile.encode(&mut w, repeat_count)?;
 
-	w.write_i16(chest_tiles.len() as i16)?;
-	for (x, y) in chest_tiles {
-		let (i, chest) = world
-			.chests
-			.iter()
-			.enumerate()
-			.find(|(_, c)| c.x as usize == x && c.y as usize == y)
-			.unwrap();
+	// Skip chest tiles that have no chest instead of panicking, the client just sees an unnamed chest
+	let chests: Vec<(usize, usize, usize)> = chest_tiles
+		.into_iter()
+		.filter_map(|(x, y)| Some((world.find_chest(x, y)?, x, y)))
+		.collect();
+	w.write_i16(chests.len() as i16)?;
+	for (i, x, y) in chests {
+		let chest = world.chests[i].as_ref().unwrap();
 		w.write_i16(i as i16)?;
 		w.write_i16(x as i16)?;
 		w.write_i16(y as i16)?;
 		w.write_string(&chest.name)?;
 	}
 
-	w.write_i16(sign_tiles.len() as i16)?;
-	for (x, y) in sign_tiles {
-		let (i, sign) = world
-			.signs
-			.iter()
-			.enumerate()
-			.find(|(_, s)| s.x as usize == x && s.y as usize == y)
-			.unwrap();
+	// Signs are created lazily when first read, so not every sign tile has one
+	let signs: Vec<(usize, usize, usize)> = sign_tiles
+		.into_iter()
+		.filter_map(|(x, y)| Some((world.find_sign(x, y)?, x, y)))
+		.collect();
+	w.write_i16(signs.len() as i16)?;
+	for (i, x, y) in signs {
+		let sign = world.signs[i].as_ref().unwrap();
 		w.write_i16(i as i16)?;
 		w.write_i16(x as i16)?;
 		w.write_i16(y as i16)?;
 		w.write_string(&sign.text)?;
 	}
 
-	w.write_i16(entity_tiles.len() as i16)?;
-	for (x, y) in entity_tiles {
-		let entity = world
-			.entities
-			.iter()
-			.find(|c| c.x == x as i16 && c.y == y as i16)
-			.unwrap();
-		entity.write(&mut w)?
+	// Host tiles get their entity from the client that placed them, which hasn't happened yet for some
+	let entities: Vec<usize> = entity_tiles.into_iter().filter_map(|(x, y)| world.find_entity(x, y)).collect();
+	w.write_i16(entities.len() as i16)?;
+	for i in entities {
+		world.entities[i].write(&mut w)?
 	}
 
 	Ok(Message::Custom(10, w.into_inner().into_inner()?.finish()?))
 }
 
+// Port of NetMessage case 86
+pub fn encode_entity(entity: &Entity) -> io::Result<Message> {
+	let mut w = Writer::new(vec![]);
+	entity.write_network(&mut w)?;
+
+	Ok(Message::UpdateTileEntity(UpdateTileEntity {
+		id: entity.id,
+		exists: true,
+		entity: w.into_inner(),
+	}))
+}
+
+pub fn encode_removed_entity(id: i32) -> Message {
+	Message::UpdateTileEntity(UpdateTileEntity {
+		id,
+		exists: false,
+		entity: vec![],
+	})
+}
+
+// Port of NetMessage.SendTileSquare, columns are sent one after the other
+pub fn encode_tile_square(world: &World, x: usize, y: usize, width: usize, height: usize) -> io::Result<Message> {
+	let mut tiles = vec![];
+	for i in x..x + width {
+		for j in y..y + height {
+			world.tiles[i][j].encode_square(&mut tiles)?;
+		}
+	}
+
+	Ok(Message::TileSquare(TileSquare {
+		x: x as i16,
+		y: y as i16,
+		width: width as u8,
+		height: height as u8,
+		change_type: 0,
+		tiles,
+	}))
+}
+
 pub fn encode_world_header(h: &Header) -> Message {
 	Message::WorldHeader(WorldHeader {
-		time: 0,
+		time: h.time as i32,
 		time_flags: flags(
 			h.day_time,
 			h.blood_moon,
@@ -370,3 +404,53 @@ pub fn encode_world_header(h: &Header) -> Message {
 		sandstorm_intended_severity: h.sandstorm_intended_severity,
 	})
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::network::codec::encode_frame;
+	use crate::world::entity::{EntityInner, EntityItem};
+
+	#[test]
+	fn update_tile_entity_leaves_out_the_id() {
+		let sensor = Entity {
+			id: 0x01020304,
+			x: 100,
+			y: 200,
+			inner: EntityInner::LogicSensor { logic_check: 3, on: true },
+		};
+		let frame = encode_frame(encode_entity(&sensor).unwrap()).unwrap();
+		#[rustfmt::skip]
+		assert_eq!(frame, [
+			15, 0, 86,
+			4, 3, 2, 1, // id
+			1, // exists
+			2, // kind
+			100, 0, 200, 0, // position
+			3, 1, // logic check, on
+		]);
+
+		let item_frame = Entity {
+			id: 7,
+			x: -1,
+			y: 300,
+			inner: EntityInner::ItemFrame(EntityItem { id: 757, stack: 1, prefix: 81 }),
+		};
+		let frame = encode_frame(encode_entity(&item_frame).unwrap()).unwrap();
+		#[rustfmt::skip]
+		assert_eq!(frame, [
+			18, 0, 86,
+			7, 0, 0, 0,
+			1,
+			1,
+			255, 255, 44, 1,
+			245, 2, 81, 1, 0, // item, prefix, stack
+		]);
+	}
+
+	#[test]
+	fn removed_tile_entity_has_no_data() {
+		let frame = encode_frame(encode_removed_entity(9)).unwrap();
+		assert_eq!(frame, [8, 0, 86, 9, 0, 0, 0, 0]);
+	}
+}
@@ -0,0 +1,51 @@
+use std::collections::HashSet;
+
+use crate::network::broadcast::{Broadcast, Scope};
+use crate::network::client::{Client, ConnectionState};
+use crate::network::items::PLAYER_SIZE;
+use crate::network::server::{send_tile_squares, Server};
+use crate::network::utils::encode_entity;
+use crate::world::types::World;
+use crate::world::wiring::Hitbox;
+
+impl Server {
+	// Hitboxes of the players in the world, for the pressure plates, sensors and dummies near them
+	pub fn player_hitboxes(clients: &[Option<Client>]) -> Vec<Hitbox> {
+		clients
+			.iter()
+			.flatten()
+			.filter(|c| c.state == ConnectionState::Complete)
+			.map(|c| {
+				let position = c.view.position();
+				(position.0, position.1, PLAYER_SIZE.0, PLAYER_SIZE.1)
+			})
+			.collect()
+	}
+
+	// Steps the sensors and the wiring and sends whatever they switched to the players that have it loaded
+	pub fn run_wiring(&self, world: &mut World, players: &[Hitbox]) {
+		let snapshot = world.section_versions.snapshot();
+		let mut areas = world.update_pressure_plates(players);
+		for i in world.update_logic_sensors(players) {
+			let entity = &world.entities[i];
+			let (x, y) = (entity.x as usize, entity.y as usize);
+			match encode_entity(entity) {
+				Ok(msg) => {
+					let _ = self.broadcast.send(Broadcast::new(msg, None, Scope::Tile(x, y)));
+				}
+				Err(e) => log_warn!("Failed to encode logic sensor at {}, {}: {}", x, y, e),
+			}
+			areas.push((x, y, 1, 1));
+		}
+		areas.extend(world.update_wiring());
+
+		// A tile hit by several wires is only sent once
+		let mut seen = HashSet::new();
+		areas.retain(|&area| seen.insert(area));
+		for &(x, y, width, height) in &areas {
+			world.frame_area(x, y, width, height);
+		}
+		// Sending fails when nobody is connected, which is fine
+		let _ = send_tile_squares(&self.broadcast, world, &areas, &snapshot);
+	}
+}
@@ -0,0 +1,277 @@
+use crate::world::tile::Tile;
+use crate::world::transpiled::items;
+use crate::world::transpiled::tile_flags::{PLATFORMS, SOLID};
+use crate::world::transpiled::tiles::{CONTAINERS, CONTAINERS_2, DRESSERS};
+use crate::world::types::{Chest, ChestItem, World, CHEST_SIZE, MAX_CHESTS};
+
+pub const CHEST_NAME_LENGTH: usize = 20; // Chest.MaxNameLength
+
+// Width in tiles of the object, chests and dressers are all 2 tiles high
+fn chest_width(id: i16) -> Option<usize> {
+	match id {
+		CONTAINERS | CONTAINERS_2 => Some(2),
+		DRESSERS => Some(3),
+		_ => None,
+	}
+}
+
+pub fn is_chest(tile: &Tile) -> bool {
+	tile.active && chest_width(tile.id).is_some()
+}
+
+// Chest.chestItemSpawn, locked chests give the chest they unlock into
+const CHEST_ITEMS: &[i16] = &[
+	items::CHEST,
+	items::GOLD_CHEST,
+	items::GOLD_CHEST,
+	items::SHADOW_CHEST,
+	items::SHADOW_CHEST,
+	items::BARREL,
+	items::TRASH_CAN,
+	items::EBONWOOD_CHEST,
+	items::RICH_MAHOGANY_CHEST,
+	items::PEARLWOOD_CHEST,
+	items::IVY_CHEST,
+	items::ICE_CHEST,
+	items::LIVING_WOOD_CHEST,
+	items::SKYWARE_CHEST,
+	items::SHADEWOOD_CHEST,
+	items::WEB_COVERED_CHEST,
+	items::LIHZAHRD_CHEST,
+	items::WATER_CHEST,
+	items::JUNGLE_CHEST,
+	items::CORRUPTION_CHEST,
+	items::CRIMSON_CHEST,
+	items::HALLOWED_CHEST,
+	items::FROZEN_CHEST,
+	items::JUNGLE_CHEST,
+	items::CORRUPTION_CHEST,
+	items::CRIMSON_CHEST,
+	items::HALLOWED_CHEST,
+	items::FROZEN_CHEST,
+	items::DYNASTY_CHEST,
+	items::HONEY_CHEST,
+	items::STEAMPUNK_CHEST,
+	items::PALM_WOOD_CHEST,
+	items::MUSHROOM_CHEST,
+	items::BOREAL_WOOD_CHEST,
+	items::SLIME_CHEST,
+	items::GREEN_DUNGEON_CHEST,
+	items::GREEN_DUNGEON_CHEST,
+	items::PINK_DUNGEON_CHEST,
+	items::PINK_DUNGEON_CHEST,
+	items::BLUE_DUNGEON_CHEST,
+	items::BLUE_DUNGEON_CHEST,
+	items::BONE_CHEST,
+	items::CACTUS_CHEST,
+	items::FLESH_CHEST,
+	items::OBSIDIAN_CHEST,
+	items::PUMPKIN_CHEST,
+	items::SPOOKY_CHEST,
+	items::GLASS_CHEST,
+	items::MARTIAN_CHEST,
+	items::METEORITE_CHEST,
+	items::GRANITE_CHEST,
+	items::MARBLE_CHEST,
+];
+
+// Chest.chestItemSpawn2
+const CHEST_ITEMS_2: &[i16] = &[
+	items::CRYSTAL_CHEST,
+	items::GOLDEN_CHEST,
+	items::SPIDER_CHEST,
+	items::LESION_CHEST,
+	items::DEAD_MANS_CHEST,
+	items::SOLAR_CHEST,
+	items::VORTEX_CHEST,
+	items::NEBULA_CHEST,
+	items::STARDUST_CHEST,
+	items::GOLF_CHEST,
+	items::DESERT_CHEST,
+	items::BAMBOO_CHEST,
+	items::DUNGEON_DESERT_CHEST,
+	items::DUNGEON_DESERT_CHEST,
+	items::CORAL_CHEST,
+	items::BALLOON_CHEST,
+	items::ASH_WOOD_CHEST,
+];
+
+// Chest.dresserItemSpawn
+const DRESSER_ITEMS: &[i16] = &[
+	items::DRESSER,
+	items::EBONWOOD_DRESSER,
+	items::RICH_MAHOGANY_DRESSER,
+	items::PEARLWOOD_DRESSER,
+	items::SHADEWOOD_DRESSER,
+	items::BLUE_DUNGEON_DRESSER,
+	items::GREEN_DUNGEON_DRESSER,
+	items::PINK_DUNGEON_DRESSER,
+	items::GOLDEN_DRESSER,
+	items::OBSIDIAN_DRESSER,
+	items::BONE_DRESSER,
+	items::CACTUS_DRESSER,
+	items::SPOOKY_DRESSER,
+	items::SKYWARE_DRESSER,
+	items::HONEY_DRESSER,
+	items::LIHZAHRD_DRESSER,
+	items::PALM_WOOD_DRESSER,
+	items::MUSHROOM_DRESSER,
+	items::BOREAL_WOOD_DRESSER,
+	items::SLIME_DRESSER,
+	items::PUMPKIN_DRESSER,
+	items::STEAMPUNK_DRESSER,
+	items::GLASS_DRESSER,
+	items::FLESH_DRESSER,
+	items::MARTIAN_DRESSER,
+	items::METEORITE_DRESSER,
+	items::GRANITE_DRESSER,
+	items::MARBLE_DRESSER,
+	items::CRYSTAL_DRESSER,
+	items::DYNASTY_DRESSER,
+	items::FROZEN_DRESSER,
+	items::LIVING_WOOD_DRESSER,
+	items::SPIDER_DRESSER,
+	items::LESION_DRESSER,
+	items::SOLAR_DRESSER,
+	items::VORTEX_DRESSER,
+	items::NEBULA_DRESSER,
+	items::STARDUST_DRESSER,
+	items::SANDSTONE_DRESSER,
+	items::BAMBOO_DRESSER,
+	items::CORAL_DRESSER,
+	items::BALLOON_DRESSER,
+	items::ASH_WOOD_DRESSER,
+];
+
+// The item WorldGen.CheckChest drops for the chest or dresser this tile belongs to, 0 for anything else
+pub fn chest_item(tile: &Tile) -> i16 {
+	let Some(width) = chest_width(tile.id).filter(|_| tile.active && tile.frame_x >= 0) else {
+		return 0;
+	};
+	let style = tile.frame_x as usize / (width * 18);
+	let table = match tile.id {
+		CONTAINERS => CHEST_ITEMS,
+		CONTAINERS_2 => CHEST_ITEMS_2,
+		_ => DRESSER_ITEMS,
+	};
+	table.get(style).copied().unwrap_or(0)
+}
+
+impl World {
+	pub fn find_chest(&self, x: usize, y: usize) -> Option<usize> {
+		self.chests
+			.iter()
+			.position(|c| c.as_ref().is_some_and(|c| c.x as usize == x && c.y as usize == y))
+	}
+
+	// Top left corner of the chest or dresser covering this tile
+	pub fn chest_origin(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+		let tile = &self.tiles[x][y];
+		let width = chest_width(tile.id).filter(|_| tile.active)?;
+		let dx = (tile.frame_x as usize % (width * 18)) / 18;
+		let dy = (tile.frame_y as usize % 36) / 18;
+		Some((x.checked_sub(dx)?, y.checked_sub(dy)?))
+	}
+
+	// Port of WorldGen.PlaceChest, x and y being the bottom left tile for chests and the bottom middle one for dressers
+	// Returns the new chest's id
+	pub fn place_chest(&mut self, x: usize, y: usize, id: i16, style: i16) -> Option<usize> {
+		let width = chest_width(id)?;
+		let left = if id == DRESSERS { x.checked_sub(1)? } else { x };
+		let top = y.checked_sub(1)?;
+		if style < 0 || !self.in_world(left as i32, top as i32, 1) || !self.in_world((left + width) as i32, y as i32 + 1, 1) {
+			return None;
+		}
+
+		let free = (left..left + width).all(|i| {
+			let ground = &self.tiles[i][y + 1];
+			!self.tiles[i][top].active
+				&& !self.tiles[i][y].active
+				&& ground.active
+				&& !ground.in_active
+				&& (SOLID[ground.id as usize] || PLATFORMS[ground.id as usize])
+		});
+		if !free {
+			return None;
+		}
+
+		let chest = Chest {
+			x: left as i32,
+			y: top as i32,
+			name: String::new(),
+			items: vec![ChestItem::default(); CHEST_SIZE as usize],
+		};
+		let index = match self.chests.iter().position(Option::is_none) {
+			Some(i) => {
+				self.chests[i] = Some(chest);
+				i
+			}
+			None if self.chests.len() < MAX_CHESTS => {
+				self.chests.push(Some(chest));
+				self.chests.len() - 1
+			}
+			None => return None,
+		};
+
+		for dx in 0..width {
+			for dy in 0..2 {
+				let tile = &mut self.tiles[left + dx][top + dy];
+				tile.clear_block();
+				tile.active = true;
+				tile.id = id;
+				tile.frame_x = style * width as i16 * 18 + dx as i16 * 18;
+				tile.frame_y = dy as i16 * 18;
+			}
+		}
+		self.area_changed(left, top, width, 2);
+
+		Some(index)
+	}
+
+	// Chests that still have items in them can't be destroyed, just like in vanilla
+	// Returns the id of the removed chest
+	pub fn kill_chest(&mut self, x: usize, y: usize) -> Option<usize> {
+		let (left, top) = self.chest_origin(x, y)?;
+		let index = self.find_chest(left, top)?;
+		if self.chests[index].as_ref().unwrap().items.iter().any(|i| i.stack > 0) {
+			return None;
+		}
+
+		let width = chest_width(self.tiles[left][top].id)?;
+		for dx in 0..width {
+			for dy in 0..2 {
+				self.tiles[left + dx][top + dy].clear_block();
+			}
+		}
+		self.area_changed(left, top, width, 2);
+		self.chests[index] = None;
+
+		Some(index)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn tile(id: i16, frame_x: i16) -> Tile {
+		Tile {
+			active: true,
+			id,
+			frame_x,
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn chests_drop_the_item_of_their_style() {
+		assert_eq!(chest_item(&tile(CONTAINERS, 0)), items::CHEST);
+		assert_eq!(chest_item(&tile(CONTAINERS, 18)), items::CHEST);
+		// Locked gold chests give a gold chest
+		assert_eq!(chest_item(&tile(CONTAINERS, 2 * 36)), items::GOLD_CHEST);
+		assert_eq!(chest_item(&tile(CONTAINERS, 51 * 36)), items::MARBLE_CHEST);
+		assert_eq!(chest_item(&tile(CONTAINERS_2, 2 * 36 + 18)), items::SPIDER_CHEST);
+		assert_eq!(chest_item(&tile(DRESSERS, 54 + 36)), items::EBONWOOD_DRESSER);
+		assert_eq!(chest_item(&tile(CONTAINERS, 60 * 36)), 0);
+	}
+}
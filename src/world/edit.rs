@@ -0,0 +1,360 @@
+use crate::world::tile::{Liquid, Tile};
+use crate::world::transpiled::tile_flags::{FRAME, PLATFORMS, SOLID};
+use crate::world::transpiled::{tiles, walls};
+use crate::world::types::{World, TILE_COUNT, WALL_COUNT};
+
+// Ports of the WorldGen functions used by MessageBuffer case 17
+// Each one returns whether the tile was changed
+
+impl Tile {
+	pub fn clear_block(&mut self) {
+		self.active = false;
+		self.id = -1;
+		self.frame_x = 0;
+		self.frame_y = 0;
+		self.color = 0;
+		self.half_brick = false;
+		self.slope = 0;
+		self.in_active = false;
+		self.invisible_block = false;
+		self.fullbright_block = false;
+	}
+
+	pub fn clear_wall(&mut self) {
+		self.wall = 0;
+		self.wall_color = 0;
+		self.invisible_wall = false;
+		self.fullbright_wall = false;
+	}
+}
+
+// Whether a tile sent in a square only differs from the server's in its frames and paint
+// Liquids stay the server's business, and inactive tiles have no id to compare
+fn square_allows(old: &Tile, tile: &Tile) -> bool {
+	let mut allowed = tile.clone();
+	allowed.frame_x = old.frame_x;
+	allowed.frame_y = old.frame_y;
+	allowed.color = old.color;
+	allowed.wall_color = old.wall_color;
+	allowed.liquid = old.liquid;
+	allowed.liquid_kind = old.liquid_kind.clone();
+	if !tile.active {
+		allowed.id = old.id;
+	}
+	allowed == *old
+}
+
+impl World {
+	pub fn in_world(&self, x: i32, y: i32, fluff: i32) -> bool {
+		x >= fluff && x < self.header.width - fluff && y >= fluff && y < self.header.height - fluff
+	}
+
+	pub fn kill_tile(&mut self, x: usize, y: usize) -> bool {
+		let tile = &mut self.tiles[x][y];
+		if !tile.active {
+			return false;
+		}
+
+		tile.clear_block();
+		self.tile_changed(x, y);
+		true
+	}
+
+	pub fn place_tile(&mut self, x: usize, y: usize, id: i16, style: u8) -> bool {
+		if !(0..TILE_COUNT).contains(&id) {
+			return false;
+		}
+
+		let tile = &mut self.tiles[x][y];
+		if tile.active {
+			return false;
+		}
+
+		// Multi-tile objects get their real frames from the tile square the client sends afterwards
+		let (frame_x, frame_y) = if !FRAME[id as usize] {
+			(-1, -1)
+		} else {
+			match id {
+				tiles::TORCHES => (0, style as i16 * 22),
+				tiles::PLATFORMS => (0, style as i16 * 18),
+				_ => (0, 0),
+			}
+		};
+
+		tile.active = true;
+		tile.id = id;
+		tile.frame_x = frame_x;
+		tile.frame_y = frame_y;
+		if SOLID[id as usize] {
+			tile.liquid = 0;
+			tile.liquid_kind = Liquid::None;
+		}
+		self.tile_changed(x, y);
+		true
+	}
+
+	pub fn replace_tile(&mut self, x: usize, y: usize, id: i16, style: u8) -> bool {
+		if !self.tiles[x][y].active || !(0..TILE_COUNT).contains(&id) {
+			return false;
+		}
+
+		// Paint, slopes and coatings survive replacement
+		let old = self.tiles[x][y].clone();
+		self.tiles[x][y].clear_block();
+		self.place_tile(x, y, id, style);
+
+		let tile = &mut self.tiles[x][y];
+		tile.color = old.color;
+		tile.invisible_block = old.invisible_block;
+		tile.fullbright_block = old.fullbright_block;
+		if SOLID[id as usize] {
+			tile.half_brick = old.half_brick;
+			tile.slope = old.slope;
+		}
+		self.tile_changed(x, y);
+		true
+	}
+
+	// The natural dungeon and temple walls can't be broken before their bosses are defeated
+	pub fn can_kill_wall(&self, x: usize, y: usize) -> bool {
+		match self.tiles[x][y].wall as i16 {
+			walls::BLUE_DUNGEON_UNSAFE
+			| walls::GREEN_DUNGEON_UNSAFE
+			| walls::PINK_DUNGEON_UNSAFE
+			| walls::BLUE_DUNGEON_SLAB_UNSAFE..=walls::GREEN_DUNGEON_TILE_UNSAFE => self.header.downed_boss_3,
+			walls::LIHZAHRD_BRICK_UNSAFE => self.header.downed_golem_boss,
+			_ => true,
+		}
+	}
+
+	// Port of MessageBuffer case 20 for squares sent by clients, tiles are given column by column
+	// A square can only restyle and repaint the tiles that are already there, anything else rejects the whole square
+	pub fn place_tile_square(&mut self, x: usize, y: usize, width: usize, height: usize, tiles: Vec<Tile>) -> bool {
+		if tiles.len() != width * height
+			|| !self.in_world(x as i32, y as i32, 1)
+			|| !self.in_world((x + width) as i32, (y + height) as i32, 1)
+		{
+			return false;
+		}
+
+		let valid = tiles
+			.iter()
+			.enumerate()
+			.all(|(k, tile)| square_allows(&self.tiles[x + k / height][y + k % height], tile));
+		if !valid {
+			return false;
+		}
+
+		for (k, tile) in tiles.into_iter().enumerate() {
+			let old = &mut self.tiles[x + k / height][y + k % height];
+			if old.active && FRAME[old.id as usize] {
+				old.frame_x = tile.frame_x;
+				old.frame_y = tile.frame_y;
+			}
+			old.color = tile.color;
+			old.wall_color = tile.wall_color;
+		}
+		self.area_changed(x, y, width, height);
+		true
+	}
+
+	pub fn kill_wall(&mut self, x: usize, y: usize) -> bool {
+		let tile = &mut self.tiles[x][y];
+		if tile.wall == 0 {
+			return false;
+		}
+
+		tile.clear_wall();
+		self.tile_changed(x, y);
+		true
+	}
+
+	pub fn place_wall(&mut self, x: usize, y: usize, wall: u16) -> bool {
+		let tile = &mut self.tiles[x][y];
+		if tile.wall != 0 || wall == 0 || wall >= WALL_COUNT {
+			return false;
+		}
+
+		tile.wall = wall;
+		self.tile_changed(x, y);
+		true
+	}
+
+	pub fn replace_wall(&mut self, x: usize, y: usize, wall: u16) -> bool {
+		let tile = &mut self.tiles[x][y];
+		if tile.wall == 0 || wall == 0 || wall >= WALL_COUNT {
+			return false;
+		}
+
+		tile.wall = wall;
+		self.tile_changed(x, y);
+		true
+	}
+
+	pub fn set_wire(&mut self, x: usize, y: usize, wire: u8, on: bool) -> bool {
+		let tile = &mut self.tiles[x][y];
+		let w = match wire {
+			1 => &mut tile.wire_1,
+			2 => &mut tile.wire_2,
+			3 => &mut tile.wire_3,
+			4 => &mut tile.wire_4,
+			_ => return false,
+		};
+
+		if *w == on {
+			return false;
+		}
+
+		*w = on;
+		self.tile_changed(x, y);
+		true
+	}
+
+	pub fn set_actuator(&mut self, x: usize, y: usize, on: bool) -> bool {
+		let tile = &mut self.tiles[x][y];
+		if tile.actuator == on {
+			return false;
+		}
+
+		tile.actuator = on;
+		self.tile_changed(x, y);
+		true
+	}
+
+	pub fn pound_tile(&mut self, x: usize, y: usize) -> bool {
+		let tile = &mut self.tiles[x][y];
+		if !tile.active || !SOLID[tile.id as usize] || PLATFORMS[tile.id as usize] {
+			return false;
+		}
+
+		tile.half_brick = !tile.half_brick;
+		tile.slope = 0;
+		self.tile_changed(x, y);
+		true
+	}
+
+	pub fn slope_tile(&mut self, x: usize, y: usize, slope: u8) -> bool {
+		let tile = &mut self.tiles[x][y];
+		if slope > 4 || !tile.active {
+			return false;
+		}
+
+		if !SOLID[tile.id as usize] && !PLATFORMS[tile.id as usize] {
+			return false;
+		}
+
+		tile.half_brick = false;
+		tile.slope = slope;
+		self.tile_changed(x, y);
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::world::binary::FileReader;
+	use crate::world::transpiled::items::PLANKED_WALL;
+	use crate::world::transpiled::tiles::{SIGNS, STONE};
+	use crate::world::transpiled::walls;
+
+	// The fixture with an empty area to build in
+	fn world() -> World {
+		let fixture = include_bytes!("../../tests/fixtures/small.wld");
+		let mut world = World::read_world_v2(&mut FileReader::new(fixture.to_vec())).unwrap();
+		for column in &mut world.tiles[5..25] {
+			for tile in &mut column[5..25] {
+				tile.clear_block();
+				tile.clear_wall();
+			}
+		}
+		world
+	}
+
+	// What a client would send back for the area, column by column
+	fn square(world: &World, x: usize, y: usize, width: usize, height: usize) -> Vec<Tile> {
+		(x..x + width).flat_map(|i| world.tiles[i][y..y + height].to_vec()).collect()
+	}
+
+	#[test]
+	fn squares_can_restyle_and_repaint() {
+		let mut world = world();
+		world.place_sign(10, 11, SIGNS, 0).unwrap();
+		world.place_tile(12, 11, STONE, 0);
+
+		let mut tiles = square(&world, 10, 10, 3, 2);
+		for tile in &mut tiles[..4] {
+			tile.frame_x += 36;
+		}
+		tiles[5].color = 4;
+		assert!(world.place_tile_square(10, 10, 3, 2, tiles));
+		assert_eq!((world.tiles[10][10].frame_x, world.tiles[11][11].frame_x), (36, 54));
+		assert_eq!(world.tiles[12][11].color, 4);
+	}
+
+	#[test]
+	fn squares_cant_remove_blocks() {
+		let mut world = world();
+		world.place_tile(10, 10, STONE, 0);
+
+		let mut tiles = square(&world, 10, 10, 1, 1);
+		tiles[0].clear_block();
+		assert!(!world.place_tile_square(10, 10, 1, 1, tiles));
+		assert!(world.tiles[10][10].active);
+	}
+
+	#[test]
+	fn squares_cant_remove_signs() {
+		let mut world = world();
+		let sign = world.place_sign(10, 11, SIGNS, 0).unwrap();
+
+		// Every tile of the square but one is left as it is
+		let mut tiles = square(&world, 10, 10, 2, 2);
+		tiles[3].clear_block();
+		assert!(!world.place_tile_square(10, 10, 2, 2, tiles));
+		assert!(world.tiles[11][11].active);
+		assert!(world.signs[sign].is_some());
+	}
+
+	#[test]
+	fn squares_cant_place_walls() {
+		let mut world = world();
+
+		let mut tiles = square(&world, 10, 10, 1, 1);
+		tiles[0].wall = 1;
+		assert!(!world.place_tile_square(10, 10, 1, 1, tiles));
+		assert_eq!(world.tiles[10][10].wall, 0);
+	}
+
+	#[test]
+	fn dungeon_walls_wait_for_skeletron() {
+		let mut world = world();
+		world.place_wall(10, 10, walls::BLUE_DUNGEON_UNSAFE as u16);
+		world.header.downed_boss_3 = false;
+		assert!(!world.can_kill_wall(10, 10));
+		world.header.downed_boss_3 = true;
+		assert!(world.can_kill_wall(10, 10));
+
+		// The safe ones players place can always be broken
+		world.header.downed_boss_3 = false;
+		world.place_wall(11, 10, walls::BLUE_DUNGEON as u16);
+		assert!(world.can_kill_wall(11, 10));
+	}
+
+	#[test]
+	fn walls_drop_their_item() {
+		let mut world = world();
+		world.place_wall(10, 10, walls::PLANKED as u16);
+		assert!(world.can_kill_wall(10, 10));
+		assert_eq!(world.tiles[10][10].get_dropped_item_wall(), PLANKED_WALL);
+		assert!(world.kill_wall(10, 10));
+		assert_eq!(world.tiles[10][10].wall, 0);
+		assert!(!world.kill_wall(10, 10));
+
+		// Unsafe dungeon bricks drop the same wall as the ones players place
+		world.place_wall(11, 10, walls::BLUE_DUNGEON_UNSAFE as u16);
+		world.place_wall(12, 10, walls::BLUE_DUNGEON as u16);
+		assert!(world.tiles[11][10].get_dropped_item_wall() > 0);
+		assert_eq!(world.tiles[11][10].get_dropped_item_wall(), world.tiles[12][10].get_dropped_item_wall());
+	}
+}
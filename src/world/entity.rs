@@ -2,7 +2,33 @@ use std::io::{self, Write};
 
 use crate::binary::writer::Writer;
 use crate::world::binary::FileReader;
-use crate::world::types::WorldDecodeError;
+use crate::world::transpiled::tiles::*;
+use crate::world::logic::overlaps;
+use crate::world::types::{World, WorldDecodeError};
+use crate::world::wiring::Hitbox;
+
+// Display dolls and hat racks number their dye slots after their item slots
+pub const DISPLAY_DOLL_SLOTS: usize = 8;
+pub const HAT_RACK_SLOTS: usize = 2;
+// Main.maxNPCs
+pub const MAX_NPCS: usize = 200;
+// How far around a dummy players keep its NPC around, in pixels
+const DUMMY_RANGE: f32 = 1600.;
+
+// The entity kind each host tile gets, from TileEntity.manager
+pub fn entity_kind(id: i16) -> Option<u8> {
+	Some(match id {
+		TARGET_DUMMY => 0,
+		ITEM_FRAME => 1,
+		LOGIC_SENSOR => 2,
+		DISPLAY_DOLL => 3,
+		WEAPONS_RACK_2 => 4,
+		HAT_RACK => 5,
+		FOOD_PLATTER => 6,
+		TELEPORTATION_PYLON => 7,
+		_ => return None,
+	})
+}
 
 #[derive(Debug, Clone)]
 pub struct Entity {
@@ -14,14 +40,14 @@ pub struct Entity {
 
 #[derive(Debug, Clone, Default)]
 pub struct DisplayDoll {
-	pub items: [EntityItem; 8],
-	pub dyes: [EntityItem; 8],
+	pub items: [EntityItem; DISPLAY_DOLL_SLOTS],
+	pub dyes: [EntityItem; DISPLAY_DOLL_SLOTS],
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct HatRack {
-	pub items: [EntityItem; 2],
-	pub dyes: [EntityItem; 2],
+	pub items: [EntityItem; HAT_RACK_SLOTS],
+	pub dyes: [EntityItem; HAT_RACK_SLOTS],
 }
 
 #[derive(Debug, Clone, Default)]
@@ -31,6 +57,12 @@ pub struct EntityItem {
 	pub prefix: u8,
 }
 
+impl EntityItem {
+	pub fn is_empty(&self) -> bool {
+		self.id == 0 || self.stack <= 0
+	}
+}
+
 #[derive(Debug, Clone)]
 pub enum EntityInner {
 	Dummy { npc: i16 },
@@ -65,9 +97,46 @@ impl Entity {
 		w.write_i16(self.y)?;
 		self.inner.write(w)
 	}
+
+	// TileEntity.Write with networkSend, the id travels in the message instead
+	pub fn write_network<T: Write>(&self, w: &mut Writer<T>) -> io::Result<()> {
+		w.write_byte(self.inner.kind())?;
+		w.write_i16(self.x)?;
+		w.write_i16(self.y)?;
+		self.inner.write(w)
+	}
 }
 
 impl EntityInner {
+	// What a freshly placed entity of this kind holds, logic sensors get their check from the tile's style
+	fn new(kind: u8, frame_y: i16) -> Option<Self> {
+		Some(match kind {
+			0 => EntityInner::Dummy { npc: -1 },
+			1 => EntityInner::ItemFrame(EntityItem::default()),
+			2 => EntityInner::LogicSensor {
+				logic_check: (frame_y / 18 + 1) as u8,
+				on: false,
+			},
+			3 => EntityInner::DisplayDoll(DisplayDoll::default()),
+			4 => EntityInner::WeaponsRack(EntityItem::default()),
+			5 => EntityInner::HatRack(HatRack::default()),
+			6 => EntityInner::FoodPlatter(EntityItem::default()),
+			7 => EntityInner::TeleportationPylon,
+			_ => return None,
+		})
+	}
+
+	// Everything the entity holds, which drops when it's destroyed
+	pub fn items(&self) -> Vec<&EntityItem> {
+		let items: Vec<&EntityItem> = match self {
+			EntityInner::ItemFrame(item) | EntityInner::WeaponsRack(item) | EntityInner::FoodPlatter(item) => vec![item],
+			EntityInner::DisplayDoll(doll) => doll.items.iter().chain(&doll.dyes).collect(),
+			EntityInner::HatRack(rack) => rack.items.iter().chain(&rack.dyes).collect(),
+			_ => vec![],
+		};
+		items.into_iter().filter(|item| !item.is_empty()).collect()
+	}
+
 	pub fn kind(&self) -> u8 {
 		match &self {
 			EntityInner::Dummy { .. } => 0,
@@ -140,15 +209,16 @@ impl EntityInner {
 				w.write_i16(rack.stack)
 			}
 			EntityInner::HatRack(rack) => {
+				// items occupy the low bits, followed by dyes
 				let mut flags = 0;
-				for item in rack.items.iter().rev() {
-					if item.id != 0 {
+				for dye in rack.dyes.iter().rev() {
+					if dye.id != 0 {
 						flags |= 1;
 					}
 					flags <<= 1;
 				}
-				for (i, dye) in rack.dyes.iter().enumerate().rev() {
-					if dye.id != 0 {
+				for (i, item) in rack.items.iter().enumerate().rev() {
+					if item.id != 0 {
 						flags |= 1;
 					}
 					if i != 0 {
@@ -260,3 +330,109 @@ impl EntityInner {
 		}
 	}
 }
+
+impl World {
+	pub fn find_entity(&self, x: usize, y: usize) -> Option<usize> {
+		self.entities.iter().position(|e| e.x as usize == x && e.y as usize == y)
+	}
+
+	pub fn find_entity_by_id(&self, id: i32) -> Option<usize> {
+		self.entities.iter().position(|e| e.id == id)
+	}
+
+	// Port of TileEntity.PlaceEntityNet, x and y have to be the top left tile of the entity's host object
+	// Returns the entity at that spot, which might have been there already
+	pub fn place_entity(&mut self, x: usize, y: usize, kind: u8) -> Option<usize> {
+		if !self.in_world(x as i32, y as i32, 1) {
+			return None;
+		}
+		let tile = &self.tiles[x][y];
+		if entity_kind(tile.id) != Some(kind) || self.object_at(x, y).is_none_or(|(ox, oy, _, _)| (ox, oy) != (x, y)) {
+			return None;
+		}
+		if let Some(i) = self.find_entity(x, y) {
+			return (self.entities[i].inner.kind() == kind).then_some(i);
+		}
+
+		let inner = EntityInner::new(kind, tile.frame_y)?;
+		// TileEntity.AssignNewID
+		let id = self.entities.iter().map(|e| e.id + 1).max().unwrap_or(0);
+		self.entities.push(Entity {
+			id,
+			x: x as i16,
+			y: y as i16,
+			inner,
+		});
+		Some(self.entities.len() - 1)
+	}
+
+	// Puts an item in a display doll or hat rack slot, returns where the entity is
+	pub fn set_entity_slot(&mut self, id: i32, host: i16, slot: usize, item: EntityItem) -> Option<(usize, usize)> {
+		let i = self.find_entity_by_id(id).filter(|&i| Some(self.entities[i].inner.kind()) == entity_kind(host))?;
+		let entity = &mut self.entities[i];
+		let held = match &mut entity.inner {
+			EntityInner::DisplayDoll(doll) if slot < DISPLAY_DOLL_SLOTS => &mut doll.items[slot],
+			EntityInner::DisplayDoll(doll) => doll.dyes.get_mut(slot - DISPLAY_DOLL_SLOTS)?,
+			EntityInner::HatRack(rack) if slot < HAT_RACK_SLOTS => &mut rack.items[slot],
+			EntityInner::HatRack(rack) => rack.dyes.get_mut(slot - HAT_RACK_SLOTS)?,
+			_ => return None,
+		};
+		*held = item;
+		Some((entity.x as usize, entity.y as usize))
+	}
+
+	// Port of TETrainingDummy.UpdateDummies, dummies get an NPC while a player is in range
+	// and lose it once nobody is, returns the dummies that changed along with the NPC slot they had before
+	pub fn update_dummies(&mut self, players: &[Hitbox]) -> Vec<(usize, i16)> {
+		if self.stale_dummies {
+			self.stale_dummies = false;
+			for entity in &mut self.entities {
+				if let EntityInner::Dummy { npc } = &mut entity.inner {
+					*npc = -1;
+				}
+			}
+		}
+
+		let mut changed = vec![];
+		for i in 0..self.entities.len() {
+			let entity = &self.entities[i];
+			let EntityInner::Dummy { npc } = entity.inner else {
+				continue;
+			};
+			let (x, y) = (entity.x as usize, entity.y as usize);
+			let tile = &self.tiles[x][y];
+			let (px, py) = (x as f32 * 16. - DUMMY_RANGE, y as f32 * 16. - DUMMY_RANGE);
+			let area = (px, py, 32. + DUMMY_RANGE * 2., 48. + DUMMY_RANGE * 2.);
+			let wanted = tile.active && tile.id == TARGET_DUMMY && players.iter().any(|&player| overlaps(player, area));
+			if wanted == (npc != -1) {
+				continue;
+			}
+
+			let slot = if wanted {
+				// Town NPCs take the first slots
+				let Some(slot) = (self.npcs.len()..MAX_NPCS).find(|&slot| self.find_dummy(slot as i16).is_none()) else {
+					continue;
+				};
+				slot as i16
+			} else {
+				-1
+			};
+			self.entities[i].inner = EntityInner::Dummy { npc: slot };
+			changed.push((i, npc));
+		}
+		changed
+	}
+
+	// The dummy entity a live NPC slot belongs to, strikes on any other NPC aren't the server's business
+	pub fn find_dummy(&self, npc: i16) -> Option<usize> {
+		self.entities
+			.iter()
+			.position(|e| matches!(e.inner, EntityInner::Dummy { npc: n } if n == npc && n != -1))
+	}
+
+	// Removes the entity whose host object has its top left tile here
+	pub fn kill_entity(&mut self, x: usize, y: usize) -> Option<Entity> {
+		let i = self.find_entity(x, y)?;
+		Some(self.entities.remove(i))
+	}
+}
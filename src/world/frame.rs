@@ -0,0 +1,201 @@
+use crate::world::tile::Tile;
+use crate::world::transpiled::tile_flags::{FRAME, PLATFORMS, SOLID};
+use crate::world::types::World;
+
+// Neither world files nor sections store the frames of blocks, clients work them out when they get a section or a square.
+// The server still keeps them in line with its own changes, but without bumping section versions since clients
+// compute the same frames. Blocks only join tiles of their own type, WorldGen.TileFrame's dirt blending and
+// tileMerge pairs aren't ported
+
+// Sides of a tile left open by its slope or half brick, as up, down, left and right
+fn open_sides(tile: &Tile) -> [bool; 4] {
+	match (tile.half_brick, tile.slope) {
+		(true, _) => [true, false, false, false],
+		// SlopeDownLeft, SlopeDownRight, SlopeUpLeft and SlopeUpRight
+		(_, 1) => [true, false, true, false],
+		(_, 2) => [true, false, false, true],
+		(_, 3) => [false, true, true, false],
+		(_, 4) => [false, true, false, true],
+		_ => [false; 4],
+	}
+}
+
+// The rectangles WorldGen.TileFrame picks for plain blocks, in 18 pixel steps
+// variant is Tile.frameNumber, which picks one of the three looks every case has
+fn block_frame(up: bool, down: bool, left: bool, right: bool, variant: i16) -> (i16, i16) {
+	let (x, y) = match (up, down, left, right) {
+		(true, true, true, true) => (1 + variant, 1),
+		(false, true, true, true) => (1 + variant, 0),
+		(true, false, true, true) => (1 + variant, 2),
+		(true, true, false, true) => (0, variant),
+		(true, true, true, false) => (4, variant),
+		(false, true, false, true) => (variant * 2, 3),
+		(false, true, true, false) => (1 + variant * 2, 3),
+		(true, false, false, true) => (variant * 2, 4),
+		(true, false, true, false) => (1 + variant * 2, 4),
+		(true, true, false, false) => (5, variant),
+		(false, false, true, true) => (6 + variant, 4),
+		(false, true, false, false) => (6 + variant, 0),
+		(true, false, false, false) => (6 + variant, 3),
+		(false, false, false, true) => (9, variant),
+		(false, false, true, false) => (12, variant),
+		(false, false, false, false) => (9 + variant, 3),
+	};
+	(x * 18, y * 18)
+}
+
+// Tiles don't keep their frameNumber, so it's picked from the position to stay the same between reframes
+fn variant(x: usize, y: usize) -> i16 {
+	((x * 7 + y * 13) % 3) as i16
+}
+
+impl World {
+	// Whether the block at x, y joins the one on its side, the sides being up, down, left and right
+	fn connects(&self, x: usize, y: usize, side: usize) -> bool {
+		let (nx, ny, opposite) = match side {
+			0 => (x, y - 1, 1),
+			1 => (x, y + 1, 0),
+			2 => (x - 1, y, 3),
+			_ => (x + 1, y, 2),
+		};
+		let (tile, other) = (&self.tiles[x][y], &self.tiles[nx][ny]);
+		other.active && other.id == tile.id && !open_sides(tile)[side] && !open_sides(other)[opposite]
+	}
+
+	// Port of WorldGen.TileFrame for blocks, slopes and platforms
+	// Multi-tile objects keep the frames they were placed with
+	pub fn frame_tile(&mut self, x: usize, y: usize) {
+		if !self.in_world(x as i32, y as i32, 1) || !self.tiles[x][y].active {
+			return;
+		}
+
+		let id = self.tiles[x][y].id;
+		if PLATFORMS[id as usize] {
+			self.tiles[x][y].frame_x = self.platform_frame(x, y);
+		} else if !FRAME[id as usize] {
+			let [up, down, left, right] = [0, 1, 2, 3].map(|side| self.connects(x, y, side));
+			let (frame_x, frame_y) = block_frame(up, down, left, right, variant(x, y));
+			let tile = &mut self.tiles[x][y];
+			tile.frame_x = frame_x;
+			tile.frame_y = frame_y;
+		}
+	}
+
+	// Platforms only look at their left and right neighbours, and whether those are platforms or blocks they're nailed to
+	fn platform_frame(&self, x: usize, y: usize) -> i16 {
+		// Stairs
+		match self.tiles[x][y].slope {
+			1 => return 144,
+			2 => return 180,
+			_ => {}
+		}
+
+		// 0 for nothing, 1 for a platform and 2 for a block
+		let kind = |x: usize| {
+			let tile = &self.tiles[x][y];
+			match tile.active && !tile.in_active {
+				true if PLATFORMS[tile.id as usize] => 1,
+				true if SOLID[tile.id as usize] => 2,
+				_ => 0,
+			}
+		};
+		match (kind(x - 1), kind(x + 1)) {
+			(1, 1) => 0,
+			(1, 0) => 18,
+			(0, 1) => 36,
+			(2, 1) => 54,
+			(1, 2) => 72,
+			(2, 0) => 108,
+			(0, 2) => 126,
+			_ => 90,
+		}
+	}
+
+	// Reframes the tiles of an area and the ones bordering it
+	// Call after changing the world so the frames match what clients would compute
+	pub fn frame_area(&mut self, x: usize, y: usize, width: usize, height: usize) {
+		for i in x.saturating_sub(1)..x + width + 1 {
+			for j in y.saturating_sub(1)..y + height + 1 {
+				self.frame_tile(i, j);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::world::binary::FileReader;
+	use crate::world::tile::Tile;
+	use crate::world::transpiled::tiles::{PLATFORMS, STONE};
+	use crate::world::types::World;
+
+	fn world() -> World {
+		let fixture = include_bytes!("../../tests/fixtures/small.wld");
+		let mut world = World::read_world_v2(&mut FileReader::new(fixture.to_vec())).unwrap();
+		for column in &mut world.tiles[5..25] {
+			for tile in &mut column[5..25] {
+				tile.clear_block();
+				tile.clear_wall();
+			}
+		}
+		world
+	}
+
+	fn place(world: &mut World, x: usize, y: usize, id: i16) {
+		world.tiles[x][y] = Tile {
+			active: true,
+			id,
+			..Default::default()
+		};
+	}
+
+	fn frame(world: &World, x: usize, y: usize) -> (i16, i16) {
+		(world.tiles[x][y].frame_x / 18, world.tiles[x][y].frame_y / 18)
+	}
+
+	#[test]
+	fn blocks_join_their_neighbours() {
+		let mut world = world();
+		for x in 10..13 {
+			place(&mut world, x, 10, STONE);
+		}
+		world.frame_area(10, 10, 3, 1);
+		// Only open up and down in the middle, open on the left or right at the ends
+		assert_eq!(frame(&world, 11, 10).1, 4);
+		assert!((6..9).contains(&frame(&world, 11, 10).0));
+		assert_eq!(frame(&world, 10, 10).0, 9);
+		assert_eq!(frame(&world, 12, 10).0, 12);
+
+		// Breaking the middle leaves two lone blocks
+		world.tiles[11][10].clear_block();
+		world.frame_area(11, 10, 1, 1);
+		assert_eq!(frame(&world, 10, 10).1, 3);
+		assert_eq!(frame(&world, 12, 10).1, 3);
+	}
+
+	#[test]
+	fn slopes_open_their_sides() {
+		let mut world = world();
+		place(&mut world, 10, 10, STONE);
+		place(&mut world, 11, 10, STONE);
+		world.frame_area(10, 10, 2, 1);
+		assert_eq!(frame(&world, 10, 10).0, 9);
+
+		// Sloping down to the left keeps the block from joining the one on its left
+		world.tiles[11][10].slope = 1;
+		world.frame_area(11, 10, 1, 1);
+		assert_eq!(frame(&world, 10, 10).1, 3);
+	}
+
+	#[test]
+	fn platforms_follow_their_neighbours() {
+		let mut world = world();
+		place(&mut world, 10, 10, STONE);
+		place(&mut world, 11, 10, PLATFORMS);
+		place(&mut world, 12, 10, PLATFORMS);
+		world.frame_area(10, 10, 3, 1);
+		// Nailed to the block on the left, then the end of the platform
+		assert_eq!(world.tiles[11][10].frame_x, 54);
+		assert_eq!(world.tiles[12][10].frame_x, 18);
+	}
+}
@@ -0,0 +1,299 @@
+use std::cmp::{min, Reverse};
+use std::collections::HashSet;
+use std::mem;
+
+use crate::world::tile::{Liquid, Tile};
+use crate::world::transpiled::tile_flags::{PLATFORMS, SOLID};
+use crate::world::transpiled::tiles::{CRISPY_HONEY_BLOCK, HONEY_BLOCK, OBSIDIAN, SHIMMER_BLOCK};
+use crate::world::types::World;
+
+// Liquid.maxLiquid, cells past this wait for the next update
+const MAX_UPDATES: usize = 25000;
+// Less liquid than this mixing with another one just disappears instead of making a block
+const MIN_MERGE_AMOUNT: u16 = 24;
+const FULL: u8 = 255;
+
+impl Liquid {
+	// Tile.liquidType
+	pub fn id(&self) -> u8 {
+		match self {
+			Self::Lava => 1,
+			Self::Honey => 2,
+			Self::Shimmer => 3,
+			Self::Water | Self::None => 0,
+		}
+	}
+
+	pub fn from_id(id: u8) -> Option<Self> {
+		Some(match id {
+			0 => Self::Water,
+			1 => Self::Lava,
+			2 => Self::Honey,
+			3 => Self::Shimmer,
+			_ => return None,
+		})
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Liquids {
+	// Cells that might still flow
+	active: HashSet<(usize, usize)>,
+	// Cells whose liquid changed since the last update, for syncing
+	changed: HashSet<(usize, usize)>,
+	// Liquid saved before it settled is looked for once, on the first update
+	found_unsettled: bool,
+}
+
+// What an update changed
+pub struct LiquidChanges {
+	pub changed: Vec<(usize, usize)>,
+	// Blocks made by liquids mixing
+	pub merged: Vec<(usize, usize)>,
+}
+
+fn blocks_liquid(tile: &Tile) -> bool {
+	tile.active && !tile.in_active && SOLID[tile.id as usize] && !PLATFORMS[tile.id as usize]
+}
+
+// Port of Liquid.GetLiquidMergeTypes, shimmer wins over everything
+fn merge_tile(a: &Liquid, b: &Liquid) -> i16 {
+	match (a, b) {
+		(Liquid::Shimmer, _) | (_, Liquid::Shimmer) => SHIMMER_BLOCK,
+		(Liquid::Water, Liquid::Lava) | (Liquid::Lava, Liquid::Water) => OBSIDIAN,
+		(Liquid::Water, Liquid::Honey) | (Liquid::Honey, Liquid::Water) => HONEY_BLOCK,
+		_ => CRISPY_HONEY_BLOCK,
+	}
+}
+
+impl World {
+	// Queues the cell and the ones that could flow into it for the next update
+	pub fn wake_liquid(&mut self, x: usize, y: usize) {
+		for (i, j) in [(x, y), (x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1))] {
+			if self.in_world(i as i32, j as i32, 1) {
+				self.liquids.active.insert((i, j));
+			}
+		}
+	}
+
+	// Sets a cell's liquid from outside the simulation, like a player's bucket
+	pub fn set_liquid(&mut self, x: usize, y: usize, amount: u8, kind: Liquid) -> bool {
+		if !self.in_world(x as i32, y as i32, 1) || blocks_liquid(&self.tiles[x][y]) {
+			return false;
+		}
+
+		self.put_liquid(x, y, amount, kind);
+		true
+	}
+
+	// Liquids reach clients through the NetModule, so the section versions stay as they are
+	fn put_liquid(&mut self, x: usize, y: usize, amount: u8, kind: Liquid) {
+		let tile = &mut self.tiles[x][y];
+		tile.liquid = amount;
+		tile.liquid_kind = if amount == 0 { Liquid::None } else { kind };
+		self.liquids.changed.insert((x, y));
+		self.wake_liquid(x, y);
+	}
+
+	// Moves every woken cell one step, a simplified Liquid.Update: liquid falls first,
+	// then evens out with lower neighbours, and turns into a block where two kinds meet
+	pub fn update_liquids(&mut self) -> LiquidChanges {
+		if !self.liquids.found_unsettled {
+			self.liquids.found_unsettled = true;
+			for x in 1..self.tiles.len() - 1 {
+				for y in 1..self.tiles[x].len() - 1 {
+					if self.is_unsettled(x, y) {
+						self.liquids.active.insert((x, y));
+					}
+				}
+			}
+		}
+
+		let mut cells: Vec<(usize, usize)> = self.liquids.active.iter().take(MAX_UPDATES).copied().collect();
+		for cell in &cells {
+			self.liquids.active.remove(cell);
+		}
+		// Bottom up so a falling column moves one cell per update
+		cells.sort_unstable_by_key(|&(x, y)| (Reverse(y), x));
+
+		let mut merged = vec![];
+		for (x, y) in cells {
+			if self.merge_liquid(x, y) {
+				merged.push((x, y));
+			} else {
+				self.flow_liquid(x, y);
+			}
+		}
+
+		LiquidChanges {
+			changed: mem::take(&mut self.liquids.changed).into_iter().collect(),
+			merged,
+		}
+	}
+
+	// Whether the liquid in this cell could still fall, spread or mix with another kind
+	fn is_unsettled(&self, x: usize, y: usize) -> bool {
+		let tile = &self.tiles[x][y];
+		if tile.liquid == 0 || blocks_liquid(tile) {
+			return false;
+		}
+
+		let below = &self.tiles[x][y + 1];
+		let open = |t: &Tile| !blocks_liquid(t);
+		(open(below) && below.liquid < FULL)
+			|| [&self.tiles[x - 1][y], &self.tiles[x + 1][y]].into_iter().any(|t| open(t) && t.liquid < tile.liquid)
+			|| [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)].into_iter().any(|(i, j)| {
+				let t = &self.tiles[i][j];
+				t.liquid > 0 && t.liquid_kind != tile.liquid_kind
+			})
+	}
+
+	// Port of Liquid.LiquidCheck, returns whether a block was made
+	fn merge_liquid(&mut self, x: usize, y: usize) -> bool {
+		let tile = &self.tiles[x][y];
+		if tile.liquid == 0 || blocks_liquid(tile) {
+			return false;
+		}
+
+		let kind = tile.liquid_kind.clone();
+		let mut total = tile.liquid as u16;
+		let mut other = None;
+		for (i, j) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+			let neighbour = &self.tiles[i][j];
+			if neighbour.liquid > 0 && neighbour.liquid_kind != kind {
+				total += neighbour.liquid as u16;
+				other = Some(neighbour.liquid_kind.clone());
+				self.put_liquid(i, j, 0, Liquid::None);
+			}
+		}
+		let Some(other) = other else {
+			return false;
+		};
+
+		self.put_liquid(x, y, 0, Liquid::None);
+		total >= MIN_MERGE_AMOUNT && !self.tiles[x][y].active && self.place_tile(x, y, merge_tile(&kind, &other), 0)
+	}
+
+	fn flow_liquid(&mut self, x: usize, y: usize) {
+		let tile = &self.tiles[x][y];
+		if tile.liquid == 0 || blocks_liquid(tile) {
+			return;
+		}
+		let kind = tile.liquid_kind.clone();
+		let amount = tile.liquid;
+
+		// Mixing already emptied any neighbour of another kind
+		let below = &self.tiles[x][y + 1];
+		if self.in_world(x as i32, y as i32 + 1, 1) && !blocks_liquid(below) && below.liquid < FULL {
+			let moved = min(amount, FULL - below.liquid);
+			let below_amount = below.liquid + moved;
+			self.put_liquid(x, y + 1, below_amount, kind.clone());
+			self.put_liquid(x, y, amount - moved, kind);
+			return;
+		}
+
+		// Only spreading to lower neighbours keeps the amounts from going back and forth
+		let sides: Vec<usize> = [x - 1, x + 1]
+			.into_iter()
+			.filter(|&i| {
+				self.in_world(i as i32, y as i32, 1) && !blocks_liquid(&self.tiles[i][y]) && self.tiles[i][y].liquid < amount
+			})
+			.collect();
+		if sides.is_empty() {
+			return;
+		}
+
+		let total = amount as usize + sides.iter().map(|&i| self.tiles[i][y].liquid as usize).sum::<usize>();
+		let level = total / (sides.len() + 1);
+		// The remainder stays in the middle
+		let center = (total - level * sides.len()) as u8;
+		if center == amount {
+			return;
+		}
+		for i in sides {
+			self.put_liquid(i, y, level as u8, kind.clone());
+		}
+		self.put_liquid(x, y, center, kind);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::world::binary::FileReader;
+	use crate::world::transpiled::tiles::STONE;
+
+	fn world() -> World {
+		let fixture = include_bytes!("../../tests/fixtures/small.wld");
+		let mut world = World::read_world_v2(&mut FileReader::new(fixture.to_vec())).unwrap();
+		for column in &mut world.tiles[5..25] {
+			for tile in &mut column[5..25] {
+				tile.clear_block();
+				tile.clear_wall();
+				tile.liquid = 0;
+				tile.liquid_kind = Liquid::None;
+			}
+		}
+		world
+	}
+
+	fn floor(world: &mut World, x: usize, y: usize, width: usize) {
+		for i in x..x + width {
+			world.tiles[i][y] = Tile {
+				active: true,
+				id: STONE,
+				..Default::default()
+			};
+		}
+	}
+
+	#[test]
+	fn columns_fall_one_cell_per_update() {
+		let mut world = world();
+		floor(&mut world, 9, 20, 3);
+		world.set_liquid(10, 10, FULL, Liquid::Water);
+		for y in 11..20 {
+			world.update_liquids();
+			assert_eq!(world.tiles[10][y].liquid, FULL);
+			assert_eq!(world.tiles[10][y - 1].liquid, 0);
+		}
+	}
+
+	#[test]
+	fn liquid_spreads_to_lower_neighbours() {
+		let mut world = world();
+		floor(&mut world, 8, 11, 5);
+		world.set_liquid(10, 10, 90, Liquid::Water);
+		world.update_liquids();
+		assert_eq!(world.tiles[10][10].liquid, 30);
+		assert_eq!(world.tiles[9][10].liquid, 30);
+		// Cells woken along with it carry on spreading in the same update
+		assert_eq!((8..13).map(|x| world.tiles[x][10].liquid as u32).sum::<u32>(), 90);
+	}
+
+	#[test]
+	fn water_over_lava_makes_obsidian() {
+		let mut world = world();
+		floor(&mut world, 9, 12, 3);
+		world.set_liquid(10, 11, FULL, Liquid::Lava);
+		world.set_liquid(10, 10, FULL, Liquid::Water);
+		let changes = world.update_liquids();
+		assert!(changes.merged.contains(&(10, 11)) || changes.merged.contains(&(10, 10)));
+
+		let obsidian = [10, 11].iter().filter(|&&y| world.tiles[10][y].active && world.tiles[10][y].id == OBSIDIAN).count();
+		assert_eq!(obsidian, 1);
+		assert!([10, 11].iter().all(|&y| world.tiles[10][y].liquid == 0));
+		assert!(changes.changed.contains(&(10, 10)) && changes.changed.contains(&(10, 11)));
+	}
+
+	#[test]
+	fn saved_liquid_flows_on_the_first_update() {
+		let mut world = world();
+		floor(&mut world, 9, 20, 3);
+		// Written straight into the tiles like a world file would, so nothing was woken
+		world.tiles[10][10].liquid = FULL;
+		world.tiles[10][10].liquid_kind = Liquid::Water;
+		world.update_liquids();
+		assert_eq!(world.tiles[10][11].liquid, FULL);
+	}
+}
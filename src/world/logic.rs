@@ -0,0 +1,147 @@
+use rand::random;
+
+use crate::world::entity::EntityInner;
+use crate::world::tile::Liquid;
+use crate::world::transpiled::tiles::{LOGIC_GATE, LOGIC_GATE_LAMP, LOGIC_SENSOR};
+use crate::world::types::World;
+use crate::world::wiring::Hitbox;
+
+// TELogicSensor.LogicCheckType
+const DAY: u8 = 1;
+const NIGHT: u8 = 2;
+const PLAYER_ABOVE: u8 = 3;
+const WATER: u8 = 4;
+const LAVA: u8 = 5;
+const HONEY: u8 = 6;
+const NEARBY_PLAYERS: u8 = 7;
+
+// Player above sensors look at a column 3 tiles wide and 10 tiles high over them
+const ABOVE_WIDTH: f32 = 3. * 16.;
+const ABOVE_HEIGHT: f32 = 10. * 16.;
+// How far from the sensor's center a player's center counts as nearby, in pixels
+const NEARBY_RANGE: f32 = 600.;
+
+// Logic gate lamps
+const LAMP_OFF: i16 = 0;
+const LAMP_ON: i16 = 18;
+const LAMP_FAULTY: i16 = 36;
+
+pub fn overlaps((x, y, width, height): Hitbox, (i, j, w, h): Hitbox) -> bool {
+	x < i + w && i < x + width && y < j + h && j < y + height
+}
+
+impl World {
+	// Port of TELogicSensor.GetState
+	fn sensor_state(&self, logic_check: u8, x: usize, y: usize, players: &[Hitbox]) -> Option<bool> {
+		let tile = &self.tiles[x][y];
+		let (px, py) = (x as f32 * 16., y as f32 * 16.);
+		Some(match logic_check {
+			DAY => self.header.day_time,
+			NIGHT => !self.header.day_time,
+			PLAYER_ABOVE => {
+				let area = (px - 16., py - ABOVE_HEIGHT, ABOVE_WIDTH, ABOVE_HEIGHT);
+				players.iter().any(|&player| overlaps(player, area))
+			}
+			WATER => tile.liquid > 0 && tile.liquid_kind == Liquid::Water,
+			LAVA => tile.liquid > 0 && tile.liquid_kind == Liquid::Lava,
+			HONEY => tile.liquid > 0 && tile.liquid_kind == Liquid::Honey,
+			NEARBY_PLAYERS => players.iter().any(|&(x, y, width, height)| {
+				let (dx, dy) = (x + width / 2. - px - 8., y + height / 2. - py - 8.);
+				dx * dx + dy * dy < NEARBY_RANGE * NEARBY_RANGE
+			}),
+			_ => return None,
+		})
+	}
+
+	// Port of TELogicSensor.UpdateLogic, sensors that flipped send a signal down their wires
+	// Returns the entities that changed, whose tile and entity have to be synced
+	pub fn update_logic_sensors(&mut self, players: &[Hitbox]) -> Vec<usize> {
+		let mut changed = vec![];
+		for i in 0..self.entities.len() {
+			let entity = &self.entities[i];
+			let EntityInner::LogicSensor { logic_check, on } = entity.inner else {
+				continue;
+			};
+			let (x, y) = (entity.x as usize, entity.y as usize);
+			let tile = &self.tiles[x][y];
+			// Left behind by a sensor that was broken
+			if !tile.active || tile.id != LOGIC_SENSOR {
+				continue;
+			}
+			let Some(state) = self.sensor_state(logic_check, x, y, players).filter(|&state| state != on) else {
+				continue;
+			};
+
+			self.entities[i].inner = EntityInner::LogicSensor { logic_check, on: state };
+			self.tiles[x][y].frame_x = if state { 18 } else { 0 };
+			self.tile_changed(x, y);
+			self.trip_wire(x, y, 1, 1);
+			changed.push(i);
+		}
+		changed
+	}
+
+	// Port of the lamp case of Wiring.HitWireSingle, returns the gate under the lamp stack
+	// and whether the lamp that was hit is a faulty one
+	pub fn toggle_lamp(&mut self, x: usize, y: usize) -> Option<(usize, usize, bool)> {
+		let tile = &mut self.tiles[x][y];
+		let faulty = tile.frame_x == LAMP_FAULTY;
+		if !faulty {
+			tile.frame_x = if tile.frame_x == LAMP_OFF { LAMP_ON } else { LAMP_OFF };
+			self.tile_changed(x, y);
+		}
+
+		let mut j = y + 1;
+		while self.in_world(x as i32, j as i32, 1) && self.tiles[x][j].active && self.tiles[x][j].id == LOGIC_GATE_LAMP {
+			j += 1;
+		}
+		let gate = &self.tiles[x][j];
+		(gate.active && gate.id == LOGIC_GATE).then_some((x, j, faulty))
+	}
+
+	// Port of Wiring.LogicGatePass for a single gate, the gate's own wires get a signal when its output flips
+	// Stacks with a faulty lamp have no output, hitting the faulty lamp fires them with the odds of the lamps that are on
+	pub fn check_gate(&mut self, x: usize, y: usize, faulty_hit: bool) -> Option<(usize, usize, usize, usize)> {
+		let (mut lit, mut total, mut faulty) = (0, 0, false);
+		let mut j = y;
+		while j > 1 && self.tiles[x][j - 1].active && self.tiles[x][j - 1].id == LOGIC_GATE_LAMP {
+			j -= 1;
+			match self.tiles[x][j].frame_x {
+				LAMP_FAULTY => faulty = true,
+				LAMP_ON => {
+					lit += 1;
+					total += 1;
+				}
+				_ => total += 1,
+			}
+		}
+
+		if total == 0 {
+			return None;
+		}
+		if faulty {
+			if faulty_hit && random::<f32>() < lit as f32 / total as f32 {
+				self.trip_wire(x, y, 1, 1);
+			}
+			return None;
+		}
+
+		let tile = &self.tiles[x][y];
+		let state = match tile.frame_y / 18 {
+			0 => lit == total,
+			1 => lit > 0,
+			2 => lit != total,
+			3 => lit == 0,
+			4 => lit == 1,
+			_ => lit != 1,
+		};
+		if state == (tile.frame_x == 18) {
+			return None;
+		}
+
+		self.tiles[x][y].frame_x = if state { 18 } else { 0 };
+		self.tile_changed(x, y);
+		self.trip_wire(x, y, 1, 1);
+		Some((x, y, 1, 1))
+	}
+}
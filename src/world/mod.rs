@@ -1,7 +1,18 @@
 pub mod binary;
+pub mod chest;
+pub mod edit;
 pub mod entity;
+pub mod frame;
+pub mod liquid;
+pub mod logic;
+pub mod object;
+pub mod section;
+pub mod sign;
 pub mod tile;
+pub mod time;
 pub mod types;
+pub mod wiring;
 pub mod transpiled;
 
 mod reader;
+mod writer;
@@ -0,0 +1,340 @@
+use crate::world::chest::is_chest;
+use crate::world::entity::Entity;
+use crate::world::tile::Tile;
+use crate::world::transpiled::tile_flags::{
+	FRAME, IS_ATREE_TRUNK, IS_VINE, PREVENTS_TILE_REMOVAL_IF_ON_TOP_OF_IT, SIGN,
+};
+use crate::world::transpiled::tiles::*;
+use crate::world::types::World;
+
+// What holds an object in place, from the anchors in TileObjectData
+#[derive(Clone, Copy, PartialEq)]
+enum Anchor {
+	// Held by walls, its neighbours or nothing at all, clients sort those out themselves
+	None,
+	// Stands on the tiles below it
+	Bottom,
+	// Hangs from the tiles above it
+	Top,
+	// Needs both, like doors
+	Both,
+}
+
+// Width and height in tiles and anchor of the frame important tiles, from TileObjectData
+// Tiles that don't keep frames are single blocks, frame important ones missing here can't be broken by the server
+fn object_data(tile: &Tile) -> Option<(usize, usize, Anchor)> {
+	use Anchor::*;
+
+	if !FRAME[tile.id as usize] {
+		return Some((1, 1, None));
+	}
+	Some(match tile.id {
+		PLANTS | CORRUPT_PLANTS | JUNGLE_PLANTS | MUSHROOM_PLANTS | PLANTS_2 | JUNGLE_PLANTS_2 | HALLOWED_PLANTS
+		| HALLOWED_PLANTS_2 | CRIMSON_PLANTS | ASH_PLANTS | GLOW_TULIP | CORAL | IMMATURE_HERBS | MATURE_HERBS
+		| BLOOMING_HERBS | DYE_PLANTS | CATTAIL | SEAWEED | SEA_OATS | BAMBOO | BOTTLES | CANDLES | WATER_CANDLE
+		| BOOKS | CLAY_POT | BOWLS | PLATINUM_CANDLE | PEACE_CANDLE | SHADOW_CANDLE | METAL_BARS | FOOD_PLATTER
+		| PRESSURE_PLATES | WEIGHTED_PRESSURE_PLATE | LAND_MINE | BEACH_PILES | GOLF_TEE | SMALL_PILES_1X_1ECHO => {
+			(1, 1, Bottom)
+		}
+		// Every tile of a tree stands on the one below, branches are handled by kill_object
+		TREES | PALM_TREE | MUSHROOM_TREES | TREE_TOPAZ..=TREE_AMBER | VANITY_TREE_SAKURA | VANITY_TREE_YELLOW_WILLOW
+		| TREE_ASH => (1, 1, Bottom),
+		CHAIRS | TOILETS | SAPLINGS | GEM_SAPLINGS | VANITY_TREE_SAKURA_SAPLINGS | VANITY_TREE_WILLOW_SAPLINGS
+		| FIREWORK | FIREWORK_FOUNTAIN => (1, 2, Bottom),
+		LAMPS | LAVA_LAMP => (1, 3, Bottom),
+		LAMPPOSTS => (1, 6, Bottom),
+		ANVILS | WORK_BENCHES | PIGGY_BANK | MYTHRIL_ANVIL | SMALL_PILES_2X_1ECHO => (2, 1, Bottom),
+		HEART | CONTAINERS | CONTAINERS_2 | FAKE_CONTAINERS | FAKE_CONTAINERS_2 | POTS | POTS_ECHO | JACKOLANTERNS
+		| PRESENTS | TOMBSTONES | KEGS | COOKING_POTS | SAFES | SKULL_LANTERNS | TRASH_CAN | CANDELABRAS
+		| PLATINUM_CANDELABRA | CRYSTAL_BALL | LEVER | MUSIC_BOXES | INLET_PUMP | OUTLET_PUMP | SINKS
+		| SNOWBALL_LAUNCHER | LIFE_FRUIT | PLANTERA_BULB | PUMPKINS | FISH_BOWL | AMMO_BOX | SHIP_IN_ABOTTLE
+		| SEAWEED_PLANTER | FIREWORKS_BOX | FISHING_CRATE | DETONATOR | PARTY_PRESENT | GOLD_GOLDFISH_BOWL
+		| PUPFISH_BOWL | LAVAFISH_BOWL | PLANT_DETRITUS_2X_2ECHO => (2, 2, Bottom),
+		STATUES | ALPHABET_STATUES | MUSHROOM_STATUE | BOULDER_STATUE | MANNEQUIN | WOMANNEQUIN | DISPLAY_DOLL
+		| TARGET_DUMMY | LUNAR_MONOLITH | BLOOD_MOON_MONOLITH | DJINN_LAMP | CAT_BAST => (2, 3, Bottom),
+		SUNFLOWER | WATER_FOUNTAIN => (2, 4, Bottom),
+		GRANDFATHER_CLOCKS => (2, 5, Bottom),
+		TELEPORTER => (3, 1, Bottom),
+		TABLES | TABLES_2 | FURNACES | DEMON_ALTAR | HELLFORGE | LOOM | PIANOS | DRESSERS | BENCHES
+		| TINKERERS_WORKBENCH | ADAMANTITE_FORGE | CAMPFIRE | BLENDOMATIC | MEAT_GRINDER | LIHZAHRD_ALTAR
+		| BUBBLE_MACHINE | HEAVY_WORK_BENCH | SHARPENING_STATION | FIREPLACE | LARGE_PILES | LARGE_PILES_2
+		| LARGE_PILES_ECHO | LARGE_PILES_2ECHO | PLANT_DETRITUS_3X_2ECHO | SNAIL_CAGE | GLOWING_SNAIL_CAGE
+		| WORM_CAGE | GOLD_WORM_CAGE | CAGE_ENCHANTED_NIGHTCRAWLER..=CAGE_SLUGGY | MAGGOT_CAGE | LADYBUG_CAGE
+		| GOLD_LADYBUG_CAGE | WATER_STRIDER_CAGE | GOLD_WATER_STRIDER_CAGE | MAGMA_SNAIL_CAGE | TRUFFLE_WORM_CAGE
+		| STINKBUG_CAGE => (3, 2, Bottom),
+		SAWMILL | EXTRACTINATOR | CHLOROPHYTE_EXTRACTINATOR | SOLIDIFIER | DYE_VAT | IMBUING_STATION | AUTOHAMMER
+		| BONE_WELDER..=STEAMPUNK_BOILER | HONEY_DISPENSER | BEWITCHING_TABLE | ALCHEMY_TABLE | SUNDIAL | MOONDIAL
+		| CHIMNEY | LUNAR_CRAFTING_STATION | VOID_VAULT | LESION_STATION => (3, 3, Bottom),
+		BOOKCASES | THRONES | HAT_RACK | TELEPORTATION_PYLON | DEFENDERS_FORGE => (3, 4, Bottom),
+		BEDS | BATHTUBS | PICNIC_TABLE => (4, 2, Bottom),
+		CANNON | DRUM_SET => (4, 3, Bottom),
+		CHRISTMAS_TREE => (4, 8, Bottom),
+		BUNNY_CAGE..=CARDINAL_CAGE | SCORPION_CAGE..=MOUSE_CAGE | PENGUIN_CAGE | GRASSHOPPER_CAGE | GOLD_BIRD_CAGE
+		| GOLD_BUNNY_CAGE | GOLD_FROG_CAGE..=GOLD_MOUSE_CAGE | SQUIRREL_ORANGE_CAGE | SQUIRREL_GOLD_CAGE | RAT_CAGE
+		| OWL_CAGE | TURTLE_CAGE | TURTLE_JUNGLE_CAGE | GREBE_CAGE | SEAGULL_CAGE
+		| AMETHYST_BUNNY_CAGE..=AMBER_SQUIRREL_CAGE | SCARLET_MACAW_CAGE | BLUE_MACAW_CAGE
+		| TOUCAN_CAGE..=GRAY_COCKATIEL_CAGE => (6, 3, Bottom),
+		// The 1x1 piles come first in the spritesheet, the 2x1 ones below them
+		SMALL_PILES if tile.frame_y < 18 => (1, 1, Bottom),
+		SMALL_PILES => (2, 1, Bottom),
+		PLANT_DETRITUS if tile.frame_y < 36 => (3, 2, Bottom),
+		PLANT_DETRITUS => (2, 2, Bottom),
+		// Stalactites hanging down and stalagmites standing up, the 1x2 ones first
+		STALACTITE => match tile.frame_y / 36 {
+			0 => (1, 2, Top),
+			1 => (1, 2, Bottom),
+			_ if tile.frame_y < 90 => (1, 1, Top),
+			_ => (1, 1, Bottom),
+		},
+		WATER_DRIP | LAVA_DRIP | HONEY_DRIP | SAND_DRIP => (1, 1, Top),
+		HANGING_LANTERNS | FIREFLYINA_BOTTLE | LIGHTNING_BUGINA_BOTTLE | LAVAFLYINA_BOTTLE | SHIMMERFLYINA_BOTTLE => {
+			(1, 2, Top)
+		}
+		BANNERS => (1, 3, Top),
+		CHINESE_LANTERNS | DISCO_BALL => (2, 2, Top),
+		CHANDELIERS => (3, 3, Top),
+		CLOSED_DOOR => (1, 3, Both),
+		TALL_GATE_CLOSED | TALL_GATE_OPEN => (1, 5, Both),
+		OPEN_DOOR => (2, 3, Both),
+		TORCHES | PLATFORMS | TEAM_BLOCK_RED_PLATFORM | TEAM_BLOCK_GREEN_PLATFORM..=TEAM_BLOCK_WHITE_PLATFORM
+		| PLANTER_BOX | MINECART_TRACK | SWITCHES | TRAPS | EXPLOSIVES | TIMERS | HOLIDAY_LIGHTS | LOGIC_GATE_LAMP
+		| LOGIC_GATE | LOGIC_SENSOR | WIRE_PIPE | WIRE_BULB | PIXEL_BOX | PROJECTILE_PRESSURE_PAD | EXPOSED_GEMS
+		| CRYSTALS | LONG_MOSS | ARROW_SIGN | PAINTED_ARROW_SIGN | LILY_PAD => (1, 1, None),
+		SHADOW_ORBS | BOULDER | ITEM_FRAME | ANNOUNCEMENT_BOX => (2, 2, None),
+		PAINTING_2X3 => (2, 3, None),
+		PAINTING_3X2 => (3, 2, None),
+		PAINTING_3X3 | WEAPONS_RACK | WEAPONS_RACK_2 | LARVA | GEM_LOCKS => (3, 3, None),
+		PAINTING_4X3 => (4, 3, None),
+		PAINTING_6X4 => (6, 4, None),
+		_ if SIGN[tile.id as usize] => (2, 2, None),
+		_ => return Option::None,
+	})
+}
+
+// Height in pixels of one style for objects whose styles are stacked vertically
+// Most tiles are 16 pixels high plus 2 of padding, some objects have a taller bottom row
+fn style_height(id: i16, height: usize) -> usize {
+	match id {
+		CHAIRS | TOILETS => 40,
+		_ => height * 18,
+	}
+}
+
+// Objects that fall when the tile they stand on is removed
+fn is_resting(tile: &Tile) -> bool {
+	tile.active && matches!(object_data(tile), Some((_, _, Anchor::Bottom | Anchor::Both)))
+}
+
+// Objects that fall when the tile they hang from is removed
+fn is_hanging(tile: &Tile) -> bool {
+	tile.active && (IS_VINE[tile.id as usize] || matches!(object_data(tile), Some((_, _, Anchor::Top | Anchor::Both))))
+}
+
+// Branches and roots are the tree tiles without more of the tree above or below them
+fn is_branch(w: &World, x: usize, y: usize, id: i16) -> bool {
+	let same = |y: usize| w.tiles[x][y].active && w.tiles[x][y].id == id;
+	w.tiles[x][y].active && w.tiles[x][y].id == id && !same(y - 1) && !same(y + 1)
+}
+
+// Everything kill_object removed
+pub struct Cascade {
+	// Origin and a copy of the top left tile of every removed object, for item drops
+	pub objects: Vec<(usize, usize, Tile)>,
+	// Bounding box of the changed tiles as x, y, width and height
+	pub area: (usize, usize, usize, usize),
+	// Tile entities of the removed objects, their items still have to be dropped
+	pub entities: Vec<Entity>,
+}
+
+impl World {
+	// Top left corner, width and height of the object covering this tile
+	pub fn object_at(&self, x: usize, y: usize) -> Option<(usize, usize, usize, usize)> {
+		let tile = &self.tiles[x][y];
+		if !tile.active {
+			return None;
+		}
+
+		let (width, height, _) = object_data(tile)?;
+		// Christmas trees keep their decorations in the frame of their top left tile, the other tiles hold their offset from it
+		if tile.id == CHRISTMAS_TREE {
+			let (dx, dy) = match tile.frame_x >= 10 {
+				true => (0, 0),
+				false => (tile.frame_x.max(0) as usize, tile.frame_y.max(0) as usize),
+			};
+			return Some((x.checked_sub(dx)?, y.checked_sub(dy)?, width, height));
+		}
+		// Styles and directions are laid out next to each other in the spritesheet, so the frame wraps around the size
+		let dx = (tile.frame_x.max(0) as usize / 18) % width;
+		let dy = (tile.frame_y.max(0) as usize % style_height(tile.id, height)) / 18;
+		Some((x.checked_sub(dx)?, y.checked_sub(dy)?, width, height))
+	}
+
+	// Port of the WorldGen.CanKillTile checks for tiles that hold up chests, trees and altars
+	pub fn can_kill_tile(&self, x: usize, y: usize) -> bool {
+		let Some((ox, oy, width, _)) = self.object_at(x, y) else {
+			return false;
+		};
+		if oy == 0 {
+			return true;
+		}
+
+		let id = self.tiles[x][y].id;
+		(ox..ox + width).all(|i| {
+			let above = &self.tiles[i][oy - 1];
+			!above.active || above.id == id || !(PREVENTS_TILE_REMOVAL_IF_ON_TOP_OF_IT[above.id as usize] || is_chest(above))
+		})
+	}
+
+	// Removes the object covering this tile along with whatever loses its support because of it:
+	// objects standing on it, objects and vines hanging from it and the rest of a felled tree
+	// Chests are never removed here since their contents have to be checked first
+	pub fn kill_object(&mut self, x: usize, y: usize) -> Option<Cascade> {
+		if is_chest(&self.tiles[x][y]) || !self.can_kill_tile(x, y) {
+			return None;
+		}
+
+		let mut objects = vec![];
+		let mut entities = vec![];
+		let (mut left, mut top, mut right, mut bottom) = (x, y, x, y);
+		let mut queue = vec![(x, y)];
+		while let Some((x, y)) = queue.pop() {
+			if is_chest(&self.tiles[x][y]) || !self.can_kill_tile(x, y) {
+				continue;
+			}
+			let Some((ox, oy, width, height)) = self.object_at(x, y) else {
+				continue;
+			};
+			if !self.in_world(ox as i32, oy as i32, 1) || !self.in_world((ox + width) as i32, (oy + height) as i32, 1) {
+				continue;
+			}
+
+			let id = self.tiles[x][y].id;
+			objects.push((ox, oy, self.tiles[ox][oy].clone()));
+			left = left.min(ox);
+			top = top.min(oy);
+			right = right.max(ox + width - 1);
+			bottom = bottom.max(oy + height - 1);
+
+			// Felling a tree takes the branches next to every trunk tile with it, the trunk above falls like any object
+			if IS_ATREE_TRUNK[id as usize] || id == PALM_TREE {
+				for i in [ox - 1, ox + 1] {
+					if is_branch(self, i, oy, id) {
+						queue.push((i, oy));
+					}
+				}
+			}
+
+			entities.extend(self.kill_entity(ox, oy));
+			if SIGN[id as usize] {
+				self.kill_sign(x, y);
+			} else {
+				for i in ox..ox + width {
+					for j in oy..oy + height {
+						if self.tiles[i][j].id == id {
+							self.kill_tile(i, j);
+						}
+					}
+				}
+			}
+
+			for i in ox..ox + width {
+				// Only objects whose bottom row stands on this one or whose top row hangs from it
+				if is_resting(&self.tiles[i][oy - 1]) && self.object_at(i, oy - 1).is_some_and(|o| o.1 + o.3 == oy) {
+					queue.push((i, oy - 1));
+				}
+				if is_hanging(&self.tiles[i][oy + height])
+					&& self.object_at(i, oy + height).is_some_and(|o| o.1 == oy + height)
+				{
+					queue.push((i, oy + height));
+				}
+			}
+		}
+
+		if objects.is_empty() {
+			return None;
+		}
+		Some(Cascade {
+			objects,
+			area: (left, top, right - left + 1, bottom - top + 1),
+			entities,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::world::binary::FileReader;
+
+	// The fixture with an empty area to build in
+	fn world() -> World {
+		let fixture = include_bytes!("../../tests/fixtures/small.wld");
+		let mut world = World::read_world_v2(&mut FileReader::new(fixture.to_vec())).unwrap();
+		for column in &mut world.tiles[5..25] {
+			for tile in &mut column[5..25] {
+				tile.clear_block();
+				tile.clear_wall();
+			}
+		}
+		world
+	}
+
+	// Places an object with x and y as its top left tile
+	fn place(world: &mut World, x: usize, y: usize, id: i16, width: usize, height: usize) {
+		for dx in 0..width {
+			for dy in 0..height {
+				let tile = &mut world.tiles[x + dx][y + dy];
+				tile.active = true;
+				tile.id = id;
+				tile.frame_x = dx as i16 * 18;
+				tile.frame_y = dy as i16 * 18;
+			}
+		}
+	}
+
+	fn block(world: &mut World, x: usize, y: usize) {
+		place(world, x, y, STONE, 1, 1);
+		world.tiles[x][y].frame_x = -1;
+		world.tiles[x][y].frame_y = -1;
+	}
+
+	#[test]
+	fn objects_fall_with_the_block_they_stand_on() {
+		let mut world = world();
+		block(&mut world, 12, 20);
+		block(&mut world, 13, 20);
+		place(&mut world, 12, 18, CHAIRS, 1, 2);
+		place(&mut world, 13, 19, TORCHES, 1, 1);
+
+		let cascade = world.kill_object(12, 20).unwrap();
+		assert_eq!(cascade.area, (12, 18, 1, 3));
+		assert!(!world.tiles[12][18].active && !world.tiles[12][19].active);
+
+		// Torches can hold on to walls, so they're left to clients
+		world.kill_object(13, 20).unwrap();
+		assert!(world.tiles[13][19].active);
+	}
+
+	#[test]
+	fn objects_fall_with_the_block_they_hang_from() {
+		let mut world = world();
+		block(&mut world, 15, 10);
+		place(&mut world, 14, 11, CHANDELIERS, 3, 3);
+		place(&mut world, 14, 14, TABLES, 3, 2);
+
+		let cascade = world.kill_object(15, 10).unwrap();
+		assert_eq!(cascade.area, (14, 10, 3, 4));
+		assert!((14..17).all(|x| (11..14).all(|y| !world.tiles[x][y].active)));
+		// The table below the chandelier doesn't stand on it
+		assert!(world.tiles[14][14].active);
+	}
+
+	#[test]
+	fn unknown_objects_are_left_alone() {
+		let mut world = world();
+		place(&mut world, 10, 10, PIGRONATA, 1, 1);
+		assert_eq!(world.object_at(10, 10), None);
+		assert!(world.kill_object(10, 10).is_none());
+		assert!(world.tiles[10][10].active);
+	}
+}
@@ -1,9 +1,13 @@
 use crate::world::binary::FileReader;
 use crate::world::entity::*;
+use crate::world::liquid::Liquids;
+use crate::world::section::SectionVersions;
 use crate::world::tile::*;
 use crate::world::types::*;
+use crate::world::wiring::Wiring;
+use std::sync::Arc;
 use std::time::{Duration, UNIX_EPOCH};
-use std::{collections::HashSet, fs, path::Path};
+use std::{fs, path::Path};
 
 use super::transpiled::tile_flags::SIGN;
 
@@ -62,7 +66,9 @@ impl World {
 			return Err(WorldDecodeError::PositionCheckFailed("signs".to_owned()));
 		}
 
-		let npcs = Self::read_npcs(r, &metadata)?;
+		let shimmered_npcs = Self::read_shimmered_npcs(r, &metadata)?;
+		let npcs = Self::read_npcs(r, &metadata, &shimmered_npcs)?;
+		let persistent_npcs = Self::read_persistent_npcs(r, &metadata)?;
 		if r.get_cur() != format.positions[5] as usize {
 			return Err(WorldDecodeError::PositionCheckFailed("npcs".to_owned()));
 		}
@@ -139,11 +145,17 @@ impl World {
 			Ok(World {
 				metadata,
 				format,
+				section_versions: Arc::new(SectionVersions::new(header.width as usize, header.height as usize)),
 				header,
 				tiles,
+				liquids: Liquids::default(),
+				wiring: Wiring::default(),
+				stale_dummies: true,
 				chests,
 				signs,
 				npcs,
+				shimmered_npcs,
+				persistent_npcs,
 				entities,
 				weighted_pressure_plates,
 				room_locations,
@@ -176,7 +188,7 @@ impl World {
 			return Err(WorldDecodeError::ExpectedWorldType);
 		}
 
-		if version > 279 {
+		if version > WORLD_VERSION {
 			return Err(WorldDecodeError::UnsupportedVersion(version));
 		}
 
@@ -279,11 +291,12 @@ impl World {
 		};
 
 		// https://learn.microsoft.com/en-us/dotnet/api/system.datetime.now?view=net-8.0#remarks
-		let creation_time = if version >= 141 {
-			let b = r.read_u64()? << 2 >> 2;
-			UNIX_EPOCH + Duration::from_millis(b / 10_000 - EPOCH_DIFFERENCE)
+		let (creation_time, creation_time_kind) = if version >= 141 {
+			let b = r.read_u64()?;
+			let ticks = (b << 2 >> 2).saturating_sub(EPOCH_DIFFERENCE * 10_000);
+			(UNIX_EPOCH + Duration::from_nanos(ticks * 100), (b >> 62) as u8)
 		} else {
-			UNIX_EPOCH
+			(UNIX_EPOCH, 0)
 		};
 
 		let moon_type = r.read_byte()? as i32;
@@ -520,6 +533,7 @@ impl World {
 			world_no_traps,
 			world_zenith,
 			creation_time,
+			creation_time_kind,
 			has_crimson,
 			hard_mode,
 			moon_type,
@@ -687,12 +701,12 @@ impl World {
 		Ok(map)
 	}
 
-	pub fn read_chests(r: &mut FileReader) -> Result<Vec<Chest>, WorldDecodeError> {
+	pub fn read_chests(r: &mut FileReader) -> Result<Vec<Option<Chest>>, WorldDecodeError> {
 		let mut chests = Vec::with_capacity(r.read_i16()? as usize);
 
 		let n_2 = r.read_i16()?;
-		let n_3 = if n_2 < 40 { n_2 } else { 40 };
-		let n_4 = if n_2 < 40 { 0 } else { n_2 - 40 };
+		let n_3 = if n_2 < CHEST_SIZE { n_2 } else { CHEST_SIZE };
+		let n_4 = if n_2 < CHEST_SIZE { 0 } else { n_2 - CHEST_SIZE };
 
 		for _ in 0..chests.capacity() {
 			let x = r.read_i32()?;
@@ -712,6 +726,8 @@ impl World {
 				};
 				items.push(item)
 			}
+			// Worlds saved with smaller chests still get every slot
+			items.resize(CHEST_SIZE as usize, ChestItem::default());
 
 			for _ in 0..n_4 {
 				if r.read_i16()? > 0 {
@@ -719,7 +735,7 @@ impl World {
 				}
 			}
 
-			chests.push(Chest { x, y, name, items })
+			chests.push(Some(Chest { x, y, name, items }))
 		}
 
 		Ok(chests)
@@ -728,7 +744,7 @@ impl World {
 	pub fn read_signs(
 		r: &mut FileReader,
 		tiles: &[Vec<Tile>],
-	) -> Result<Vec<Sign>, WorldDecodeError> {
+	) -> Result<Vec<Option<Sign>>, WorldDecodeError> {
 		let mut signs = Vec::with_capacity(r.read_i16()? as usize);
 
 		for _ in 0..signs.capacity() {
@@ -738,25 +754,33 @@ impl World {
 
 			let t = &tiles[x as usize][y as usize];
 			if t.active && SIGN[t.id as usize] {
-				signs.push(Sign { x, y, text })
+				signs.push(Some(Sign { x, y, text }))
 			}
 		}
 
 		Ok(signs)
 	}
 
-	pub fn read_npcs(
+	pub fn read_shimmered_npcs(
 		r: &mut FileReader,
 		metadata: &Metadata,
-	) -> Result<Vec<NPC>, WorldDecodeError> {
-		let version = metadata.version;
-		let mut shimmers = HashSet::new();
-		if version >= 268 {
+	) -> Result<Vec<i32>, WorldDecodeError> {
+		let mut shimmers = vec![];
+		if metadata.version >= 268 {
 			for _ in 0..r.read_i32()? {
-				shimmers.insert(r.read_i32()?);
+				shimmers.push(r.read_i32()?);
 			}
 		}
 
+		Ok(shimmers)
+	}
+
+	pub fn read_npcs(
+		r: &mut FileReader,
+		metadata: &Metadata,
+		shimmers: &[i32],
+	) -> Result<Vec<NPC>, WorldDecodeError> {
+		let version = metadata.version;
 		let mut npcs = vec![];
 
 		while r.read_bool()? {
@@ -789,17 +813,28 @@ impl World {
 			})
 		}
 
+		Ok(npcs)
+	}
+
+	pub fn read_persistent_npcs(
+		r: &mut FileReader,
+		metadata: &Metadata,
+	) -> Result<Vec<PersistentNPC>, WorldDecodeError> {
+		let version = metadata.version;
+		let mut npcs = vec![];
+
 		if version >= 140 {
-			let mut iter = npcs.iter_mut();
 			while r.read_bool()? {
-				let Some(npc) = iter.next() else { break };
-				if version >= 190 {
-					npc.id = r.read_i32()?;
+				let id = if version >= 190 {
+					r.read_i32()?
 				} else {
 					todo!("implement NPCID.FromLegacyName(reader.ReadString())")
-				}
+				};
 
-				npc.position = r.read_vector2()?
+				npcs.push(PersistentNPC {
+					id,
+					position: r.read_vector2()?,
+				})
 			}
 		}
 
@@ -0,0 +1,84 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::network::utils::{get_section_x, get_section_y};
+use crate::world::types::World;
+
+// A change to a section that a broadcast carries, clients that had the version before it now have the one after
+#[derive(Debug, Clone)]
+pub struct SectionChange {
+	pub x: usize,
+	pub y: usize,
+	pub from: u32,
+	pub to: u32,
+}
+
+// How many times each section was changed, shared with the connection tasks so they can tell when a client's copy is stale
+// Versions start at 1, clients keep 0 for sections they were never sent
+#[derive(Debug)]
+pub struct SectionVersions {
+	versions: Vec<Vec<AtomicU32>>,
+}
+
+impl SectionVersions {
+	pub fn new(width: usize, height: usize) -> Self {
+		SectionVersions {
+			versions: (0..get_section_x(width) + 1)
+				.map(|_| (0..get_section_y(height) + 1).map(|_| AtomicU32::new(1)).collect())
+				.collect(),
+		}
+	}
+
+	// Size in sections
+	pub fn size(&self) -> (usize, usize) {
+		(self.versions.len(), self.versions[0].len())
+	}
+
+	pub fn get(&self, sec_x: usize, sec_y: usize) -> u32 {
+		self.versions[sec_x][sec_y].load(Ordering::Relaxed)
+	}
+
+	// Taken before changing tiles, to tell which sections the change went on to bump
+	pub fn snapshot(&self) -> Vec<Vec<u32>> {
+		self.versions.iter().map(|column| column.iter().map(|v| v.load(Ordering::Relaxed)).collect()).collect()
+	}
+
+	pub fn changes_since(&self, snapshot: &[Vec<u32>]) -> Vec<SectionChange> {
+		let mut changes = vec![];
+		for (x, column) in snapshot.iter().enumerate() {
+			for (y, &from) in column.iter().enumerate() {
+				let to = self.get(x, y);
+				if to != from {
+					changes.push(SectionChange { x, y, from, to });
+				}
+			}
+		}
+		changes
+	}
+
+	fn bump(&self, sec_x: usize, sec_y: usize) {
+		// Wrapping to 0 only makes clients get the section again
+		self.versions[sec_x][sec_y].fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+impl World {
+	// Call after changing a tile, so clients holding a copy of its section get a new one
+	// and liquids around it can flow into the space it opened up
+	pub fn tile_changed(&mut self, x: usize, y: usize) {
+		self.section_versions.bump(get_section_x(x), get_section_y(y));
+		self.wake_liquid(x, y);
+	}
+
+	pub fn area_changed(&mut self, x: usize, y: usize, width: usize, height: usize) {
+		for i in get_section_x(x)..=get_section_x(x + width - 1) {
+			for j in get_section_y(y)..=get_section_y(y + height - 1) {
+				self.section_versions.bump(i, j);
+			}
+		}
+		for i in x..x + width {
+			for j in y..y + height {
+				self.wake_liquid(i, j);
+			}
+		}
+	}
+}
@@ -0,0 +1,94 @@
+use crate::world::transpiled::tile_flags::SIGN;
+use crate::world::types::{Sign, World, MAX_SIGNS};
+
+pub fn is_sign(id: i16) -> bool {
+	id >= 0 && SIGN.get(id as usize).is_some_and(|s| *s)
+}
+
+impl World {
+	pub fn find_sign(&self, x: usize, y: usize) -> Option<usize> {
+		self.signs
+			.iter()
+			.position(|s| s.as_ref().is_some_and(|s| s.x as usize == x && s.y as usize == y))
+	}
+
+	// Top left corner of the sign covering this tile, every sign is 2x2
+	pub fn sign_origin(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+		let tile = &self.tiles[x][y];
+		if !tile.active || !SIGN[tile.id as usize] {
+			return None;
+		}
+
+		let dx = (tile.frame_x as usize % 36) / 18;
+		let dy = (tile.frame_y as usize % 36) / 18;
+		Some((x.checked_sub(dx)?, y.checked_sub(dy)?))
+	}
+
+	// Port of Sign.ReadSign with CreateIfMissing, returns the sign's id
+	pub fn read_sign(&mut self, x: usize, y: usize) -> Option<usize> {
+		let (x, y) = self.sign_origin(x, y)?;
+		if let Some(i) = self.find_sign(x, y) {
+			return Some(i);
+		}
+
+		let sign = Sign {
+			x: x as i32,
+			y: y as i32,
+			text: String::new(),
+		};
+		match self.signs.iter().position(Option::is_none) {
+			Some(i) => {
+				self.signs[i] = Some(sign);
+				Some(i)
+			}
+			None if self.signs.len() < MAX_SIGNS => {
+				self.signs.push(Some(sign));
+				Some(self.signs.len() - 1)
+			}
+			None => None,
+		}
+	}
+
+	// Places the whole 2x2 sign with x and y as its bottom left tile, like TileObjectData.Style2x2
+	// Signs on walls and ceilings use other frames, which the client corrects with a tile square
+	pub fn place_sign(&mut self, x: usize, y: usize, id: i16, style: u8) -> Option<usize> {
+		if !is_sign(id) || y == 0 || !self.in_world(x as i32 + 1, y as i32, 1) {
+			return None;
+		}
+		if (x..x + 2).any(|i| (y - 1..=y).any(|j| self.tiles[i][j].active)) {
+			return None;
+		}
+
+		for dx in 0..2 {
+			for dy in 0..2 {
+				let tile = &mut self.tiles[x + dx][y - 1 + dy];
+				tile.clear_block();
+				tile.active = true;
+				tile.id = id;
+				tile.frame_x = style as i16 * 36 + dx as i16 * 18;
+				tile.frame_y = dy as i16 * 18;
+			}
+		}
+		self.area_changed(x, y - 1, 2, 2);
+
+		self.read_sign(x, y - 1)
+	}
+
+	// Removes the whole sign and its text, returns the removed sign's id if it had one
+	pub fn kill_sign(&mut self, x: usize, y: usize) -> Option<usize> {
+		let (x, y) = self.sign_origin(x, y)?;
+		for dx in 0..2 {
+			for dy in 0..2 {
+				let tile = &mut self.tiles[x + dx][y + dy];
+				if tile.active && SIGN[tile.id as usize] {
+					tile.clear_block();
+				}
+			}
+		}
+		self.area_changed(x, y, 2, 2);
+
+		let i = self.find_sign(x, y)?;
+		self.signs[i] = None;
+		Some(i)
+	}
+}
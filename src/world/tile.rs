@@ -1,5 +1,7 @@
 use std::io::{self, Write};
 
+use crate::binary::reader::Reader;
+use crate::binary::types::ReadError;
 use crate::world::binary::FileReader;
 use crate::world::types::{WorldDecodeError, WALL_COUNT};
 
@@ -15,7 +17,7 @@ pub enum Liquid {
 	None,
 }
 
-#[derive(Debug, Clone, Default, PartialEq)]
+#[derive(Debug, Clone, Default)]
 pub struct Tile {
 	// pub header: [u8; 4], // remove this later
 	pub id: i16, // https://terraria.fandom.com/wiki/Tile_IDs
@@ -41,6 +43,36 @@ pub struct Tile {
 	pub slope: u8,
 }
 
+// Port of Tile.isTheSameAs, frames only count for tiles that save them
+impl PartialEq for Tile {
+	fn eq(&self, other: &Self) -> bool {
+		let frames = !self.active
+			|| !tile_flags::FRAME[self.id as usize]
+			|| (self.frame_x == other.frame_x && self.frame_y == other.frame_y);
+
+		self.id == other.id
+			&& self.active == other.active
+			&& frames
+			&& self.color == other.color
+			&& self.wall == other.wall
+			&& self.wall_color == other.wall_color
+			&& self.liquid == other.liquid
+			&& self.liquid_kind == other.liquid_kind
+			&& self.wire_1 == other.wire_1
+			&& self.wire_2 == other.wire_2
+			&& self.wire_3 == other.wire_3
+			&& self.wire_4 == other.wire_4
+			&& self.actuator == other.actuator
+			&& self.in_active == other.in_active
+			&& self.invisible_block == other.invisible_block
+			&& self.invisible_wall == other.invisible_wall
+			&& self.fullbright_block == other.fullbright_block
+			&& self.fullbright_wall == other.fullbright_wall
+			&& self.half_brick == other.half_brick
+			&& self.slope == other.slope
+	}
+}
+
 impl Tile {
 	pub fn decode(r: &mut FileReader) -> Result<(Self, usize), WorldDecodeError> {
 		let h_1 = r.read_byte()?;
@@ -316,4 +348,118 @@ impl Tile {
 
 		Ok(())
 	}
+
+	// Reads a tile of a client's tile square, ids aren't checked here
+	pub fn decode_square(r: &mut Reader) -> Result<Self, ReadError> {
+		let b_1 = r.read_byte()?;
+		let b_2 = r.read_byte()?;
+		let b_3 = r.read_byte()?;
+
+		let mut tile = Tile {
+			active: b_1 & 1 == 1,
+			id: -1,
+			wire_1: b_1 & 16 == 16,
+			half_brick: b_1 & 32 == 32,
+			actuator: b_1 & 64 == 64,
+			in_active: b_1 & 128 == 128,
+			wire_2: b_2 & 1 == 1,
+			wire_3: b_2 & 2 == 2,
+			slope: (b_2 >> 4) & 7,
+			wire_4: b_2 & 128 == 128,
+			fullbright_block: b_3 & 1 == 1,
+			fullbright_wall: b_3 & 2 == 2,
+			invisible_block: b_3 & 4 == 4,
+			invisible_wall: b_3 & 8 == 8,
+			..Default::default()
+		};
+
+		if b_2 & 4 == 4 {
+			tile.color = r.read_byte()?;
+		}
+		if b_2 & 8 == 8 {
+			tile.wall_color = r.read_byte()? as u16;
+		}
+
+		if tile.active {
+			tile.id = r.read_u16()? as i16;
+			if tile_flags::FRAME.get(tile.id as usize).is_some_and(|f| *f) {
+				tile.frame_x = r.read_i16()?;
+				tile.frame_y = r.read_i16()?;
+			} else {
+				tile.frame_x = -1;
+				tile.frame_y = -1;
+			}
+		}
+
+		if b_1 & 4 == 4 {
+			tile.wall = r.read_u16()?;
+		}
+
+		if b_1 & 8 == 8 {
+			tile.liquid = r.read_byte()?;
+			tile.liquid_kind = match r.read_byte()? {
+				1 => Liquid::Lava,
+				2 => Liquid::Honey,
+				3 => Liquid::Shimmer,
+				_ => Liquid::Water,
+			};
+		}
+
+		Ok(tile)
+	}
+
+	// The tile format of SendTileSquare, which differs from the one of sections and world files
+	pub fn encode_square(&self, w: &mut impl Write) -> io::Result<()> {
+		let color = self.active && self.color > 0;
+		let wall_color = self.wall > 0 && self.wall_color > 0;
+		let b_1 = self.active as u8
+			| ((self.wall > 0) as u8) << 2
+			| ((self.liquid > 0) as u8) << 3
+			| (self.wire_1 as u8) << 4
+			| (self.half_brick as u8) << 5
+			| (self.actuator as u8) << 6
+			| (self.in_active as u8) << 7;
+		let b_2 = self.wire_2 as u8
+			| (self.wire_3 as u8) << 1
+			| (color as u8) << 2
+			| (wall_color as u8) << 3
+			| self.slope << 4
+			| (self.wire_4 as u8) << 7;
+		let b_3 = self.fullbright_block as u8
+			| (self.fullbright_wall as u8) << 1
+			| (self.invisible_block as u8) << 2
+			| (self.invisible_wall as u8) << 3;
+		w.write_all(&[b_1, b_2, b_3])?;
+
+		if color {
+			w.write_all(&[self.color])?;
+		}
+		if wall_color {
+			w.write_all(&[self.wall_color as u8])?;
+		}
+
+		if self.active {
+			w.write_all(&(self.id as u16).to_le_bytes())?;
+			if tile_flags::FRAME[self.id as usize] {
+				w.write_all(&self.frame_x.to_le_bytes())?;
+				w.write_all(&self.frame_y.to_le_bytes())?;
+			}
+		}
+
+		if self.wall > 0 {
+			w.write_all(&self.wall.to_le_bytes())?;
+		}
+
+		if self.liquid > 0 {
+			let kind = match self.liquid_kind {
+				Liquid::Lava => 1,
+				Liquid::Honey => 2,
+				Liquid::Shimmer => 3,
+				_ => 0,
+			};
+			w.write_all(&[self.liquid, kind])?;
+		}
+
+		Ok(())
+	}
 }
@@ -0,0 +1,56 @@
+use crate::world::types::World;
+
+// Main.dayLength and Main.nightLength, in ticks
+pub const DAY_LENGTH: f64 = 54000.;
+pub const NIGHT_LENGTH: f64 = 32400.;
+pub const MOON_PHASES: i32 = 8;
+// Main.dayRate while a sundial or moondial is active
+const FAST_FORWARD_RATE: f64 = 60.;
+
+// Port of Main.UpdateTime without the invasion, event and NPC spawning side effects
+impl World {
+	// Advances the clock by one tick and returns whether day turned into night or vice versa
+	pub fn update_time(&mut self) -> bool {
+		let h = &mut self.header;
+		h.time += if h.fast_forward_time_to_dawn || h.fast_forward_time_to_dusk {
+			FAST_FORWARD_RATE
+		} else {
+			1.
+		};
+
+		if h.day_time && h.time >= DAY_LENGTH {
+			self.start_night();
+			true
+		} else if !h.day_time && h.time >= NIGHT_LENGTH {
+			self.start_day();
+			true
+		} else {
+			false
+		}
+	}
+
+	// Main.UpdateTime_StartDay
+	pub fn start_day(&mut self) {
+		let h = &mut self.header;
+		h.time = 0.;
+		h.day_time = true;
+		h.moon_phase = (h.moon_phase + 1) % MOON_PHASES;
+		h.blood_moon = false;
+		h.fast_forward_time_to_dawn = false;
+		if h.sundial_cooldown > 0 {
+			h.sundial_cooldown -= 1;
+		}
+		if h.moondial_cooldown > 0 {
+			h.moondial_cooldown -= 1;
+		}
+	}
+
+	// Main.UpdateTime_StartNight
+	pub fn start_night(&mut self) {
+		let h = &mut self.header;
+		h.time = 0.;
+		h.day_time = false;
+		h.eclipse = false;
+		h.fast_forward_time_to_dusk = false;
+	}
+}
@@ -1,22 +1,52 @@
 // This file was mostly auto-generated by scripts/convert_drop_items.py
 // Irregular cases were done by hand
+#![allow(clippy::collapsible_match)]
 
 use crate::world::tile::Tile;
 use crate::network::client::Client;
+use crate::world::chest::chest_item;
+use crate::world::transpiled::item_flags::TORCHES;
 use crate::world::transpiled::items;
 use crate::world::transpiled::tiles;
-use crate::world::types::World;
+use crate::world::transpiled::tile_flags::SOLID;
+use crate::world::transpiled::walls;
+use crate::world::types::{GameMode, World};
 use crate::network::utils::rr;
 use rand::random;
 
 // From Minecart.cs
 const TRACK_TYPE: &[i16] = &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 2, 2, 2, 2, 2, 2];
 
+// Main.wallDungeon
+const WALL_DUNGEON: &[i16] = &[
+	walls::BLUE_DUNGEON_UNSAFE,
+	walls::GREEN_DUNGEON_UNSAFE,
+	walls::PINK_DUNGEON_UNSAFE,
+	walls::BLUE_DUNGEON_SLAB_UNSAFE,
+	walls::BLUE_DUNGEON_TILE_UNSAFE,
+	walls::PINK_DUNGEON_SLAB_UNSAFE,
+	walls::PINK_DUNGEON_TILE_UNSAFE,
+	walls::GREEN_DUNGEON_SLAB_UNSAFE,
+	walls::GREEN_DUNGEON_TILE_UNSAFE,
+];
+
+// The first solid tile below x, y, which decides the wood trees and palms drop
+fn ground_below(w: &World, x: usize, y: usize) -> Option<i16> {
+	w.tiles[x][y + 1..].iter().find(|t| t.active && SOLID[t.id as usize]).map(|t| t.id)
+}
+
+// The out-params of KillTile_GetItemDrops that go along with the item
+pub struct TileDrop {
+	pub stack: i16,
+	pub secondary_item: i16,
+	pub secondary_stack: i16,
+}
+
 impl Tile {
-	pub fn get_dropped_item_tile(&self, c: &Client, w: &World, stack: &mut i16, secondary_item: &mut i16, secondary_stack: &mut i16) -> i16 {
-		*stack = 1;
-		*secondary_item = 0;
-		*secondary_stack = 1;
+	pub fn get_dropped_item_tile(&self, c: &Client, w: &World, x: usize, y: usize, out: &mut TileDrop) -> i16 {
+		out.stack = 1;
+		out.secondary_item = 0;
+		out.secondary_stack = 1;
 		match self.id {
 			0 | 2 | 109 | 199 | 477 | 492 => 2,
 			1 => 3,
@@ -52,7 +82,26 @@ impl Tile {
 					_ => 426 + num1,
 				}
 			}
-			5 | 596 | 616 | 634 => unimplemented!("caller should implement this because there are multiple tile drops. source in WorldGen.cs:43272"),
+			5 | 596 | 616 | 634 => {
+				let wood = match (self.id, ground_below(w, x, y)) {
+					(634, _) => items::ASH_WOOD,
+					(5, Some(tiles::CORRUPT_GRASS | tiles::CORRUPT_JUNGLE_GRASS)) => items::EBONWOOD,
+					(5, Some(tiles::CRIMSON_GRASS | tiles::CRIMSON_JUNGLE_GRASS)) => items::SHADEWOOD,
+					(5, Some(tiles::JUNGLE_GRASS)) => items::RICH_MAHOGANY,
+					(5, Some(tiles::HALLOWED_GRASS | tiles::GOLF_GRASS_HALLOWED)) => items::PEARLWOOD,
+					(5, Some(tiles::SNOW_BLOCK)) => items::BOREAL_WOOD,
+					(5, Some(tiles::MUSHROOM_GRASS)) => items::GLOWING_MUSHROOM,
+					_ => items::WOOD,
+				};
+
+				// Tree tops drop acorns half of the time, mushroom trees don't grow from them
+				if self.frame_x >= 22 && self.frame_y >= 198 && wood != items::GLOWING_MUSHROOM && random() {
+					out.stack = rr(1, 2);
+					items::ACORN
+				} else {
+					wood
+				}
+			}
 			6 => 11,
 			7 => 12,
 			8 => 13,
@@ -156,7 +205,7 @@ impl Tile {
 				if self.id == tiles::JUNGLE_PLANTS {
 					let item = match self.frame_x {
 						144 => {
-							*stack = rr(2, 3);
+							out.stack = rr(2, 3);
 							items::JUNGLE_SPORES
 						},
 						162 => items::NATURES_GIFT,
@@ -206,9 +255,9 @@ impl Tile {
 				};
 
 				if c.has_in_hand(items::STAFF_OF_REGROWTH) || c.has_in_hand(items::ACORN_AXE) {
-					*stack = rr(1, 2);
-					*secondary_item = seed;
-					*secondary_stack = rr(1, 5);
+					out.stack = rr(1, 2);
+					out.secondary_item = seed;
+					out.secondary_stack = rr(1, 5);
 				} else {
 					let harvestable = self.id == 84 || match style {
 						0 => w.header.day_time,
@@ -219,8 +268,8 @@ impl Tile {
 						_ => false,
 					};
 					if harvestable {
-						*secondary_item = seed;
-						*secondary_stack = rr(1, 3);
+						out.secondary_item = seed;
+						out.secondary_stack = rr(1, 3);
 					}
 				}
 
@@ -311,7 +360,8 @@ impl Tile {
 			168 => 701,
 			169 => 702,
 			170 => 1872,
-			171 => unimplemented!("caller should implement this because there are multiple tile drops. source in source in WorldGen.cs:43957"),
+			// Only the origin drops the tree, its decorations are in get_dropped_items_tile
+			171 if self.frame_x >= 10 => items::CHRISTMAS_TREE,
 			174 => 713,
 			175 => 717,
 			176 => 718,
@@ -425,33 +475,17 @@ impl Tile {
 			322 => 2504,
 			323 => {
 				if w.header.world_anniversary {
-					*stack += rr(2, 4)
+					out.stack += rr(2, 4)
 				}
 				if (88..=132).contains(&self.frame_x) {
-					*secondary_item = items::ACORN;
+					out.secondary_item = items::ACORN;
+				}
+				match ground_below(w, x, y) {
+					Some(tiles::EBONSAND) => items::EBONWOOD,
+					Some(tiles::PEARLSAND) => items::PEARLWOOD,
+					Some(tiles::CRIMSAND) => items::SHADEWOOD,
+					_ => items::PALM_WOOD,
 				}
-				items::PALM_WOOD
-
-				// todo:
-				// int index3 = x;
-				// int index4 = y;
-				// while (!Main.tile[index3, index4].active() || !Main.tileSolid[(int) Main.tile[index3, index4].type])
-				//   ++index4;
-				// if (!Main.tile[index3, index4].active())
-				//   break;
-				// switch (Main.tile[index3, index4].type)
-				// {
-				//   case 112:
-				//     dropItem = 619;
-				//     return;
-				//   case 116:
-				//     dropItem = 621;
-				//     return;
-				//   case 234:
-				//     dropItem = 911;
-				//     return;
-				//   default:
-				//     return;
 			}
 			324 => match self.frame_x / 18 {
 				0 => 2625,
@@ -600,7 +634,7 @@ impl Tile {
 			563 => 4547,
 			566 => 999,
 			571 => {
-				*stack = rr(1, 2);
+				out.stack = rr(1, 2);
 				items::BAMBOO_BLOCK
 			}
 			574 => 4717,
@@ -621,10 +655,10 @@ impl Tile {
 					_ => unreachable!(),
 				};
 				if self.frame_x >= 22 && self.frame_y >= 198 && random() {
-					*secondary_item = self.id - 583 + items::GEM_TREE_TOPAZ_SEED;
+					out.secondary_item = self.id - 583 + items::GEM_TREE_TOPAZ_SEED;
 				}
 				if random::<f64>() < 1. / 10. {
-					*stack = rr(1, 2);
+					out.stack = rr(1, 2);
 					gem_type
 				} else {
 					items::STONE_BLOCK
@@ -702,4 +736,438 @@ impl Tile {
 			_ => 0,
 		}
 	}
+	// Everything breaking this tile drops as (item, stack) pairs, including the drops get_dropped_item_tile can't return
+	pub fn get_dropped_items_tile(&self, c: &Client, w: &World, x: usize, y: usize) -> Vec<(i16, i16)> {
+		let mut out = TileDrop {
+			stack: 1,
+			secondary_item: 0,
+			secondary_stack: 1,
+		};
+		let item = self.get_dropped_item_tile(c, w, x, y, &mut out);
+
+		let mut drops = vec![(item, out.stack), (out.secondary_item, out.secondary_stack)];
+		match self.id {
+			// The decorations are stored in the frame_y bits of the tree's origin
+			tiles::CHRISTMAS_TREE if self.frame_x >= 10 => {
+				let bits = self.frame_y as u16;
+				let decorations = [
+					(bits & 7, items::STAR_TOPPER_1, items::BOW_TOPPER),
+					(bits >> 3 & 7, items::WHITE_GARLAND, items::GREEN_AND_WHITE_GARLAND),
+					(bits >> 6 & 15, items::MULTICOLORED_BULB, items::WHITE_AND_GREEN_BULB),
+					(bits >> 10 & 15, items::MULTICOLORED_LIGHTS, items::BLUE_AND_YELLOW_LIGHTS),
+				];
+				for (style, first, last) in decorations {
+					if style > 0 && first + style as i16 - 1 <= last {
+						drops.push((first + style as i16 - 1, 1));
+					}
+				}
+			}
+			// WorldGen.CheckChest drops the chest once, for its top left tile
+			tiles::CONTAINERS | tiles::CONTAINERS_2 | tiles::DRESSERS if self.frame_y == 0 => {
+				let width = if self.id == tiles::DRESSERS { 54 } else { 36 };
+				if self.frame_x % width == 0 {
+					drops.push((chest_item(self), 1));
+				}
+			}
+			// WorldGen.CheckPot spawns the loot once, for the top left tile
+			tiles::POTS if self.frame_x % 36 == 0 && self.frame_y % 36 == 0 => {
+				drops.extend(spawn_things_from_pot(self, c, w, y, self.frame_y / 36));
+			}
+			_ => {}
+		}
+
+		drops.retain(|&(item, stack)| item > 0 && stack > 0);
+		drops
+	}
+}
+
+// Port of WorldGen.SpawnThingsFromPot for the pot whose top left tile is at x, y
+// The coin portal and the bomb of for the worthy worlds are projectiles, which the server doesn't spawn,
+// and the biome torches only follow the pot's style since the server doesn't track the zones players are in
+fn spawn_things_from_pot(pot: &Tile, c: &Client, w: &World, y: usize, style: i16) -> Vec<(i16, i16)> {
+	let y = y as f64;
+	let underworld = (w.header.height - 200) as f64;
+	let (above_rock, above_underworld) = match w.header.world_remix {
+		true => (y > w.header.rock_layer && y < underworld, y > w.header.world_surface && y < w.header.rock_layer),
+		false => (y < w.header.rock_layer, y < underworld),
+	};
+	let expert = matches!(w.header.game_mode, GameMode::Expert | GameMode::Master);
+
+	let multiplier: f32 = match style {
+		4..=6 => 1.25,
+		7..=9 => 1.75,
+		10..=12 => 1.9,
+		13..=15 => 2.1,
+		16..=18 => 1.6,
+		19..=21 => 3.5,
+		22..=24 => 1.6,
+		25..=27 => 10.,
+		28..=30 if w.header.hard_mode => 4.,
+		31..=33 => 2.,
+		34..=36 => 1.25,
+		_ => 1.,
+	};
+	let multiplier = (multiplier * 2. + 1.) / 3.;
+
+	let mut drops = vec![];
+	if rr(0, 34) == 0 && WALL_DUNGEON.contains(&(pot.wall as i16)) && y > w.header.world_surface {
+		drops.push((items::GOLDEN_KEY, 1));
+	} else if rr(0, 44) == 0 || rr(0, 44) == 0 && expert {
+		// The potions are picked by depth, each list ends with its chances at recall potions
+		let potions: &[i16] = if y < w.header.world_surface {
+			&[
+				items::IRONSKIN_POTION,
+				items::SHINE_POTION,
+				items::NIGHT_OWL_POTION,
+				items::SWIFTNESS_POTION,
+				items::MINING_POTION,
+				items::CALMING_POTION,
+				items::BUILDER_POTION,
+				items::RECALL_POTION,
+				items::RECALL_POTION,
+				items::RECALL_POTION,
+			]
+		} else if above_rock {
+			&[
+				items::REGENERATION_POTION,
+				items::SHINE_POTION,
+				items::NIGHT_OWL_POTION,
+				items::SWIFTNESS_POTION,
+				items::ARCHERY_POTION,
+				items::GILLS_POTION,
+				items::HUNTER_POTION,
+				items::MINING_POTION,
+				items::TRAPSIGHT_POTION,
+				items::RECALL_POTION,
+				items::RECALL_POTION,
+			]
+		} else if above_underworld {
+			&[
+				items::SPELUNKER_POTION,
+				items::FEATHERFALL_POTION,
+				items::NIGHT_OWL_POTION,
+				items::WATER_WALKING_POTION,
+				items::ARCHERY_POTION,
+				items::GRAVITATION_POTION,
+				items::THORNS_POTION,
+				items::WATER_WALKING_POTION,
+				items::INVISIBILITY_POTION,
+				items::HUNTER_POTION,
+				items::MINING_POTION,
+				items::HEARTREACH_POTION,
+				items::FLIPPER_POTION,
+				items::TRAPSIGHT_POTION,
+				items::RECALL_POTION,
+			]
+		} else {
+			&[
+				items::SPELUNKER_POTION,
+				items::FEATHERFALL_POTION,
+				items::MANA_REGENERATION_POTION,
+				items::OBSIDIAN_SKIN_POTION,
+				items::MAGIC_POWER_POTION,
+				items::INVISIBILITY_POTION,
+				items::HUNTER_POTION,
+				items::GRAVITATION_POTION,
+				items::THORNS_POTION,
+				items::WATER_WALKING_POTION,
+				items::OBSIDIAN_SKIN_POTION,
+				items::BATTLE_POTION,
+				items::HEARTREACH_POTION,
+				items::RECALL_POTION,
+			]
+		};
+		let potion = potions[rr(0, potions.len() - 1)];
+		drops.push((potion, if potion == items::RECALL_POTION { rr(1, 2) } else { 1 }));
+	} else if rr(0, 29) == 0 {
+		drops.push((items::WORMHOLE_POTION, 1));
+	} else {
+		let kind = rr(0, 6) - expert as i32;
+		let torches: i32 = c
+			.iter_inventory()
+			.flatten()
+			.filter(|slot| TORCHES[slot.item_id as usize])
+			.map(|slot| slot.amount as i32)
+			.sum();
+		let hurt = c.health.as_ref().is_some_and(|h| h.current < h.maximum);
+		let drained = c.mana.as_ref().is_some_and(|m| m.current < m.maximum);
+
+		if kind == 0 && hurt {
+			let hearts = 1 + rr(0, 1) + if expert { rr(0, 1) + rr(0, 1) } else { 0 };
+			drops.extend((0..hearts).map(|_| (items::HEART, 1)));
+		} else if kind == 1 && drained {
+			drops.push((items::STAR, 1));
+		} else if kind == 2 || torches < 20 {
+			let mut stack = rr(2, 6) + if expert { rr(1, 6) } else { 0 };
+			let (mut torch, mut glowstick) = (items::TORCH, items::GLOWSTICK);
+			match style {
+				22..=24 => torch = items::CRIMSON_TORCH,
+				16..=18 => torch = items::CORRUPT_TORCH,
+				7..=9 => {
+					stack = ((stack + rr(2, 6)) as f32 * 1.5) as i16;
+					torch = items::JUNGLE_TORCH;
+				}
+				4..=6 => (torch, glowstick) = (items::ICE_TORCH, items::STICKY_GLOWSTICK),
+				34..=36 => torch = items::DESERT_TORCH,
+				_ => {}
+			}
+			if matches!(style, 22..=24 | 16..=18 | 34..=36) {
+				stack += rr(2, 6);
+			}
+			drops.push((if pot.liquid > 0 { glowstick } else { torch }, stack));
+		} else if kind == 3 {
+			let ammo = if above_rock && random() {
+				if w.header.hard_mode { items::GRENADE } else { items::SHURIKEN }
+			} else if y > underworld {
+				items::HELLFIRE_ARROW
+			} else if w.header.hard_mode && random() {
+				if w.header.ore_tier_silver == tiles::TUNGSTEN as i32 { items::TUNGSTEN_BULLET } else { items::SILVER_BULLET }
+			} else if w.header.hard_mode {
+				items::UNHOLY_ARROW
+			} else {
+				items::WOODEN_ARROW
+			};
+			drops.push((ammo, rr(10, 20)));
+		} else if kind == 4 {
+			let potion = if y > underworld || w.header.hard_mode { items::HEALING_POTION } else { items::LESSER_HEALING_POTION };
+			drops.push((potion, if expert && rr(0, 2) != 0 { 2 } else { 1 }));
+		} else if kind == 5 && y > w.header.rock_layer {
+			drops.push((items::BOMB, rr(1, 4) + if expert { rr(0, 3) } else { 0 }));
+		} else {
+			let mut value = (200 + rr(-100, 100)) as f32;
+			if y < w.header.world_surface {
+				value *= 0.5;
+			} else if y < w.header.rock_layer {
+				value *= 0.75;
+			} else if y > (w.header.height - 250) as f64 {
+				value *= 1.25;
+			}
+			value *= 1. + rr(-20, 20) as f32 * 0.01;
+			for (chance, low, high) in [(4, 5, 10), (8, 10, 20), (12, 20, 40), (16, 40, 80), (20, 50, 100)] {
+				if rr(0, chance - 1) == 0 {
+					value *= 1. + rr(low, high) as f32 * 0.01;
+				}
+			}
+			if expert {
+				value *= 2.5;
+			}
+			value *= multiplier;
+			let h = &w.header;
+			for downed in [
+				h.downed_boss_1,
+				h.downed_boss_2,
+				h.downed_boss_3,
+				h.downed_mech_boss_1,
+				h.downed_mech_boss_2,
+				h.downed_mech_boss_3,
+				h.downed_plant_boss,
+				h.downed_queen_bee,
+				h.downed_golem_boss,
+				h.downed_pirates,
+				h.downed_goblins,
+				h.downed_frost,
+			] {
+				if downed {
+					value *= 1.1;
+				}
+			}
+			drops.extend(coins(value as i32));
+		}
+	}
+	drops
+}
+
+// The coin stacks of NPC.NPCLoot_DropMoney, which pots share
+fn coins(mut value: i32) -> Vec<(i16, i16)> {
+	let mut drops = vec![];
+	while value > 0 {
+		let (coin, worth) = match value {
+			1000001.. => (items::PLATINUM_COIN, 1000000),
+			10001.. => (items::GOLD_COIN, 10000),
+			101.. => (items::SILVER_COIN, 100),
+			_ => (items::COPPER_COIN, 1),
+		};
+		let mut stack = value / worth;
+		if stack > 50 && random() {
+			stack /= rr(1, 3);
+		}
+		if random() {
+			stack /= rr(1, if coin == items::COPPER_COIN { 4 } else { 3 });
+		}
+		let stack = stack.max(1);
+		value -= worth * stack;
+		drops.push((coin, stack as i16));
+	}
+	drops
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::network::client::ClientView;
+	use crate::network::messages::PlayerItemSlot;
+	use crate::network::outbox::{self, LagPolicy};
+	use crate::world::binary::FileReader;
+	use std::sync::Arc;
+
+	fn world() -> World {
+		let fixture = include_bytes!("../../../tests/fixtures/small.wld");
+		let mut world = World::read_world_v2(&mut FileReader::new(fixture.to_vec())).unwrap();
+		// Anniversary worlds add to the palm wood stacks
+		world.header.world_anniversary = false;
+		// Expert worlds add to the pot loot
+		world.header.game_mode = GameMode::Normal;
+		world
+	}
+
+	fn client(world: &World) -> Client {
+		let view = Arc::new(ClientView::new(Arc::clone(&world.section_versions)));
+		let (outbox, _) = outbox::channel(0, 1, LagPolicy::Drop, Arc::clone(&view));
+		Client::new(([127, 0, 0, 1], 7777).into(), outbox, view)
+	}
+
+	fn tile(id: i16, frame_x: i16, frame_y: i16) -> Tile {
+		Tile {
+			active: true,
+			id,
+			frame_x,
+			frame_y,
+			..Default::default()
+		}
+	}
+
+	// Puts the tile at 10, 10 on top of ground and returns what it drops
+	fn drops(world: &mut World, tile: Tile, ground: i16) -> Vec<(i16, i16)> {
+		world.tiles[10][11] = self::tile(ground, 0, 0);
+		world.tiles[10][10] = tile.clone();
+		let client = client(world);
+		tile.get_dropped_items_tile(&client, world, 10, 10)
+	}
+
+	#[test]
+	fn blocks() {
+		let mut world = world();
+		assert_eq!(drops(&mut world, tile(tiles::DIRT, -1, -1), tiles::STONE), [(items::DIRT_BLOCK, 1)]);
+		assert_eq!(drops(&mut world, tile(tiles::STONE, -1, -1), tiles::STONE), [(items::STONE_BLOCK, 1)]);
+	}
+
+	#[test]
+	fn trees_drop_the_wood_of_their_ground() {
+		let mut world = world();
+		assert_eq!(drops(&mut world, tile(tiles::TREES, 0, 0), tiles::GRASS), [(items::WOOD, 1)]);
+		assert_eq!(drops(&mut world, tile(tiles::TREES, 0, 0), tiles::CORRUPT_GRASS), [(items::EBONWOOD, 1)]);
+		assert_eq!(drops(&mut world, tile(tiles::PALM_TREE, 0, 0), tiles::SAND), [(items::PALM_WOOD, 1)]);
+		assert_eq!(drops(&mut world, tile(tiles::PALM_TREE, 0, 0), tiles::EBONSAND), [(items::EBONWOOD, 1)]);
+	}
+
+	#[test]
+	fn christmas_tree_drops_its_decorations() {
+		let mut world = world();
+		// Star topper 1 and the second garland
+		let origin = tile(tiles::CHRISTMAS_TREE, 10, 1 | 2 << 3);
+		assert_eq!(
+			drops(&mut world, origin, tiles::DIRT),
+			[(items::CHRISTMAS_TREE, 1), (items::STAR_TOPPER_1, 1), (items::WHITE_GARLAND + 1, 1)]
+		);
+		assert_eq!(drops(&mut world, tile(tiles::CHRISTMAS_TREE, 0, 0), tiles::DIRT), []);
+	}
+
+	#[test]
+	fn herbs_drop_seeds_once_blooming() {
+		let mut world = world();
+		// Style 2 is moonglow, which doesn't bloom by itself
+		assert_eq!(drops(&mut world, tile(tiles::MATURE_HERBS, 36, 0), tiles::GRASS), [(items::DAYBLOOM + 2, 1)]);
+
+		let blooming = drops(&mut world, tile(tiles::BLOOMING_HERBS, 36, 0), tiles::GRASS);
+		assert_eq!(blooming[0], (items::DAYBLOOM + 2, 1));
+		assert_eq!(blooming[1].0, items::DAYBLOOM_SEEDS + 2);
+		assert!((1..=3).contains(&blooming[1].1));
+	}
+
+	#[test]
+	fn stacks() {
+		let mut world = world();
+		for _ in 0..20 {
+			let spores = drops(&mut world, tile(tiles::JUNGLE_PLANTS, 144, 0), tiles::JUNGLE_GRASS);
+			assert!(matches!(spores[..], [(items::JUNGLE_SPORES, 2..=3)]));
+			let bamboo = drops(&mut world, tile(tiles::BAMBOO, 0, 0), tiles::MUD);
+			assert!(matches!(bamboo[..], [(items::BAMBOO_BLOCK, 1..=2)]));
+		}
+	}
+
+	#[test]
+	fn styles_offset_the_item() {
+		let mut world = world();
+		assert_eq!(drops(&mut world, tile(tiles::TORCHES, 0, 0), tiles::DIRT), [(items::TORCH, 1)]);
+		assert_eq!(drops(&mut world, tile(tiles::TORCHES, 0, 22), tiles::DIRT), [(items::BLUE_TORCH, 1)]);
+		assert_eq!(drops(&mut world, tile(tiles::TORCHES, 0, 8 * 22), tiles::DIRT), [(items::CURSED_TORCH, 1)]);
+	}
+
+	// Pot loot is random, so these only look at what a few hundred pots can drop
+	fn pot_drops(world: &mut World, style: i16, liquid: u8) -> Vec<Vec<(i16, i16)>> {
+		let mut pot = tile(tiles::POTS, 0, style * 36);
+		pot.liquid = liquid;
+		(0..300).map(|_| drops(world, pot.clone(), tiles::DIRT)).collect()
+	}
+
+	fn is_torch(item: i16) -> bool {
+		TORCHES[item as usize] || item == items::GLOWSTICK || item == items::STICKY_GLOWSTICK
+	}
+
+	#[test]
+	fn pots_drop_once_for_their_top_left_tile() {
+		let mut world = world();
+		assert_eq!(drops(&mut world, tile(tiles::POTS, 18, 0), tiles::DIRT), []);
+		assert_eq!(drops(&mut world, tile(tiles::POTS, 0, 18), tiles::DIRT), []);
+		assert!(pot_drops(&mut world, 0, 0).iter().all(|drops| !drops.is_empty()));
+	}
+
+	#[test]
+	fn pots_drop_torches_of_their_style() {
+		let mut world = world();
+		// Without torches in the inventory every pot that doesn't roll a potion drops some
+		let torches = |drops: Vec<Vec<(i16, i16)>>| drops.into_iter().flatten().filter(|&(item, _)| is_torch(item)).collect::<Vec<_>>();
+
+		let plain = torches(pot_drops(&mut world, 0, 0));
+		assert!(plain.len() > 200);
+		assert!(plain.iter().all(|&drop| matches!(drop, (items::TORCH, 2..=6))));
+
+		let jungle = torches(pot_drops(&mut world, 7, 0));
+		assert!(jungle.iter().all(|&drop| matches!(drop, (items::JUNGLE_TORCH, 6..=18))));
+
+		let ice = torches(pot_drops(&mut world, 4, 0));
+		assert!(ice.iter().all(|&drop| matches!(drop, (items::ICE_TORCH, 2..=6))));
+		let underwater = torches(pot_drops(&mut world, 4, 255));
+		assert!(underwater.iter().all(|&drop| matches!(drop, (items::STICKY_GLOWSTICK, 2..=6))));
+	}
+
+	#[test]
+	fn pots_drop_coins_once_players_carry_torches() {
+		let mut world = world();
+		world.tiles[10][11] = tile(tiles::DIRT, 0, 0);
+		let pot = tile(tiles::POTS, 0, 0);
+		let mut client = client(&world);
+		client.items[0] = Some(PlayerItemSlot {
+			client_id: 0,
+			slot_id: 0,
+			amount: 99,
+			prefix: 0,
+			item_id: items::TORCH,
+		});
+
+		let drops: Vec<_> = (0..300).map(|_| pot.get_dropped_items_tile(&client, &world, 10, 10)).collect();
+		// More than one coin stack comes out whenever the stacks get divided
+		let only_coins = |drops: &Vec<(i16, i16)>| drops.iter().all(|&(item, _)| (items::COPPER_COIN..=items::SILVER_COIN).contains(&item));
+		assert!(drops.iter().any(|drops| drops.len() > 1 && only_coins(drops)));
+		// Torches are down to one roll in seven
+		assert!(drops.iter().filter(|drops| drops.iter().any(|&(item, _)| is_torch(item))).count() < 100);
+	}
+
+	#[test]
+	fn coins_add_up_to_the_value() {
+		for value in [1, 99, 150, 12345, 2000000] {
+			let worth = |coin: i16| 100i32.pow((coin - items::COPPER_COIN) as u32);
+			assert_eq!(coins(value).iter().map(|&(coin, stack)| worth(coin) * stack as i32).sum::<i32>(), value);
+		}
+	}
 }
@@ -192,8 +192,8 @@ impl Tile {
 			146 => 2432,
 			147 => 2433,
 			148 => 2434,
-			116..=125 => self.id + 1832,
-			126..=132 => self.id + 1882,
+			116..=125 => self.wall as i16 + 1832,
+			126..=132 => self.wall as i16 + 1882,
 			22 => 417,
 			23 => 418,
 			24 => 419,
@@ -244,8 +244,8 @@ impl Tile {
 			98 | 104 => 1382,
 			99 | 105 => 1383,
 			241 => 4260,
-			88..=93 => self.id + 1179,
-			138..=141 => self.id + 2072,
+			88..=93 => self.wall as i16 + 1179,
+			138..=141 => self.wall as i16 + 2072,
 			_ => 0,
 		}
 	}
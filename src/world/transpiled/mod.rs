@@ -3,6 +3,7 @@ pub mod tiles;
 pub mod walls;
 pub mod invasions;
 pub mod tile_flags;
+pub mod item_flags;
 
 mod drop_tile_items;
 mod drop_wall_items;
@@ -1,13 +1,19 @@
+use std::sync::Arc;
 use std::{
 	error::Error,
-	fmt, io,
+	fmt,
+	io::{self, Write},
 	str::{self, Utf8Error},
 	time::SystemTime,
 };
 
 use crate::binary::types::Vector2;
+use crate::binary::writer::Writer;
 use crate::world::entity::Entity;
+use crate::world::liquid::Liquids;
+use crate::world::section::SectionVersions;
 use crate::world::tile::Tile;
+use crate::world::wiring::Wiring;
 
 use super::binary::FileReader;
 
@@ -52,6 +58,7 @@ impl From<u8> for GameMode {
 }
 
 pub const MAGIC_STRING: &[u8] = "relogic".as_bytes();
+pub const WORLD_VERSION: i32 = 279;
 
 #[derive(Debug)]
 pub enum WorldDecodeError {
@@ -107,9 +114,19 @@ pub struct World {
 	pub format: Format,
 	pub header: Header,
 	pub tiles: Vec<Vec<Tile>>,
-	pub chests: Vec<Chest>,
-	pub signs: Vec<Sign>,
+	// Bumped by every tile change, see section.rs
+	pub section_versions: Arc<SectionVersions>,
+	pub liquids: Liquids,
+	// Signals still travelling along wires and running timers, see wiring.rs
+	pub wiring: Wiring,
+	// Dummies hold the NPC slots they were saved with until the first update, those NPCs are long gone
+	pub stale_dummies: bool,
+	// Chest and sign ids are indices, so removed ones leave a hole instead of shifting the others
+	pub chests: Vec<Option<Chest>>,
+	pub signs: Vec<Option<Sign>>,
 	pub npcs: Vec<NPC>,
+	pub shimmered_npcs: Vec<i32>,
+	pub persistent_npcs: Vec<PersistentNPC>,
 	pub entities: Vec<Entity>,
 	pub weighted_pressure_plates: Vec<WeightedPressurePlate>,
 	pub room_locations: Vec<RoomLocation>,
@@ -150,6 +167,7 @@ pub struct Header {
 	pub world_no_traps: bool,
 	pub world_zenith: bool,
 	pub creation_time: SystemTime,
+	pub creation_time_kind: u8, // DateTimeKind from the top two bits of DateTime.ToBinary
 	pub has_crimson: bool,
 	pub hard_mode: bool,
 	pub moon_type: i32,
@@ -297,7 +315,13 @@ pub struct Format {
 	pub positions: Vec<i32>,
 }
 
+pub const TILE_COUNT: i16 = 693; // TileID.Count
 pub const WALL_COUNT: u16 = 347; // WallID.Count
+pub const ITEM_COUNT: i16 = 5456; // ItemID.Count
+
+pub const CHEST_SIZE: i16 = 40; // Chest.maxItems
+pub const MAX_CHESTS: usize = 8000; // Main.maxChests
+pub const MAX_SIGNS: usize = 1000; // Sign.maxSigns
 
 #[derive(Debug, Clone)]
 pub struct Chest {
@@ -333,6 +357,13 @@ pub struct NPC {
 	pub variation: i32,
 }
 
+// NPCs that are saved regardless of being town NPCs (NPCID.Sets.SavesAndLoads)
+#[derive(Debug, Clone)]
+pub struct PersistentNPC {
+	pub id: i32,
+	pub position: Vector2,
+}
+
 #[derive(Debug, Clone)]
 pub struct WeightedPressurePlate {
 	pub x: i32,
@@ -395,4 +426,42 @@ impl CreativePower {
 			_ => Err(WorldDecodeError::InvalidCreativePower),
 		}
 	}
+
+	pub fn encode_file<T: Write>(&self, w: &mut Writer<T>) -> io::Result<()> {
+		match self {
+			CreativePower::FreezeTime(b) => {
+				w.write_i16(0)?;
+				w.write_bool(*b)
+			}
+			CreativePower::StartDayImmediately => w.write_i16(1),
+			CreativePower::StartNoonImmediately => w.write_i16(2),
+			CreativePower::StartNightImmediately => w.write_i16(3),
+			CreativePower::StartMidnightImmediately => w.write_i16(4),
+			CreativePower::GodmodePower => w.write_i16(5),
+			CreativePower::ModifyWindDirectionAndStrength => w.write_i16(6),
+			CreativePower::ModifyRainPower => w.write_i16(7),
+			CreativePower::ModifyTimeRate(f) => {
+				w.write_i16(8)?;
+				w.write_f32(*f)
+			}
+			CreativePower::FreezeRainPower(b) => {
+				w.write_i16(9)?;
+				w.write_bool(*b)
+			}
+			CreativePower::FreezeWindDirectionAndStrength(b) => {
+				w.write_i16(10)?;
+				w.write_bool(*b)
+			}
+			CreativePower::FarPlacementRangePower => w.write_i16(11),
+			CreativePower::DifficultySliderPower(f) => {
+				w.write_i16(12)?;
+				w.write_f32(*f)
+			}
+			CreativePower::StopBiomeSpreadPower(b) => {
+				w.write_i16(13)?;
+				w.write_bool(*b)
+			}
+			CreativePower::SpawnRateSliderPerPlayerPower => w.write_i16(14),
+		}
+	}
 }
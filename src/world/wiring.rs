@@ -0,0 +1,429 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::mem;
+
+use crate::world::chest::is_chest;
+use crate::world::tile::Tile;
+use crate::world::transpiled::tile_flags::{NOT_REALLY_SOLID, SOLID};
+use crate::world::transpiled::tiles::*;
+use crate::world::types::{WeightedPressurePlate, World};
+
+// Tiles a signal can reach per tick, the rest of a huge contraption carries on in the next ticks
+const MAX_WIRE_STEPS: usize = 2000;
+// The lihzahrd temple door stays shut until the golem altar is opened with a key
+const LOCKED_DOOR_STYLE: i16 = 11;
+
+// A player's x, y, width and height in pixels
+pub type Hitbox = (f32, f32, f32, f32);
+
+// Ticks between signals of a running timer, by style
+fn timer_interval(tile: &Tile) -> u32 {
+	match tile.frame_x / 18 {
+		0 => 60,
+		1 => 180,
+		2 => 300,
+		3 => 30,
+		_ => 15,
+	}
+}
+
+fn has_wire(tile: &Tile, wire: u8) -> bool {
+	match wire {
+		1 => tile.wire_1,
+		2 => tile.wire_2,
+		3 => tile.wire_3,
+		_ => tile.wire_4,
+	}
+}
+
+// Swaps a door tile for one of the other door, the way replace_tile does, only paint and coatings carry over
+fn set_door_tile(tile: &mut Tile, door: &Tile, id: i16, frame_x: i16, frame_y: i16) {
+	tile.clear_block();
+	tile.active = true;
+	tile.id = id;
+	tile.frame_x = frame_x;
+	tile.frame_y = frame_y;
+	tile.color = door.color;
+	tile.invisible_block = door.invisible_block;
+	tile.fullbright_block = door.fullbright_block;
+}
+
+// Objects wires turn on and off by moving their frame over by their width
+fn is_light(id: i16) -> bool {
+	matches!(
+		id,
+		CANDLES
+			| WATER_CANDLE
+			| PLATINUM_CANDLE
+			| PEACE_CANDLE
+			| SHADOW_CANDLE
+			| CHANDELIERS
+			| JACKOLANTERNS
+			| HANGING_LANTERNS
+			| LAMPPOSTS
+			| LAMPS
+			| CHINESE_LANTERNS
+			| CANDELABRAS
+			| PLATINUM_CANDELABRA
+			| DISCO_BALL
+			| FIREFLYINA_BOTTLE
+			| LIGHTNING_BUGINA_BOTTLE
+			| LAVAFLYINA_BOTTLE
+			| SHIMMERFLYINA_BOTTLE
+	)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Wiring {
+	signals: VecDeque<Signal>,
+	// Running timers and the ticks until they next fire
+	timers: HashMap<(usize, usize), u32>,
+	// Timers saved while running are looked for once, on the first update
+	found_timers: bool,
+	// Logic gates whose lamps changed, checked once the signals are done so a gate fires at most once per tick
+	// Along with whether a faulty lamp was hit
+	gates: HashMap<(usize, usize), bool>,
+}
+
+// A signal travelling along one wire colour, one tile per step
+#[derive(Debug, Clone)]
+struct Signal {
+	wire: u8,
+	queue: VecDeque<(usize, usize)>,
+	visited: HashSet<(usize, usize)>,
+	// The tiles of whatever sent the signal, which it doesn't hit itself
+	source: HashSet<(usize, usize)>,
+	// Origins of the objects already hit, multi-tile objects only react once
+	hit: HashSet<(usize, usize)>,
+}
+
+impl World {
+	// Port of Wiring.HitSwitch, returns the area of the switch if there was one
+	// Timers only start or stop, everything else sends a signal down its wires
+	pub fn hit_switch(&mut self, x: usize, y: usize) -> Option<(usize, usize, usize, usize)> {
+		let (ox, oy, width, height) = self.object_at(x, y)?;
+		let id = self.tiles[x][y].id;
+		match id {
+			SWITCHES => {
+				let tile = &mut self.tiles[x][y];
+				tile.frame_y = if tile.frame_y == 0 { 18 } else { 0 };
+			}
+			LEVER => {
+				let delta = if self.tiles[ox][oy].frame_x % 72 < 36 { 36 } else { -36 };
+				for i in ox..ox + width {
+					for j in oy..oy + height {
+						self.tiles[i][j].frame_x += delta;
+					}
+				}
+			}
+			TIMERS => {
+				let tile = &mut self.tiles[x][y];
+				tile.frame_y = if tile.frame_y == 0 { 18 } else { 0 };
+				if tile.frame_y == 0 {
+					self.wiring.timers.remove(&(x, y));
+				} else {
+					let interval = timer_interval(tile);
+					self.wiring.timers.insert((x, y), interval);
+				}
+				self.tile_changed(x, y);
+				return Some((x, y, 1, 1));
+			}
+			PRESSURE_PLATES | WEIGHTED_PRESSURE_PLATE => {}
+			_ => return None,
+		}
+
+		self.area_changed(ox, oy, width, height);
+		self.trip_wire(ox, oy, width, height);
+		Some((ox, oy, width, height))
+	}
+
+	// Port of Wiring.TripWire, sends a signal down every wire colour leaving the area
+	// The signals travel on the following ticks
+	pub fn trip_wire(&mut self, x: usize, y: usize, width: usize, height: usize) {
+		let source: HashSet<(usize, usize)> =
+			(x..x + width).flat_map(|i| (y..y + height).map(move |j| (i, j))).collect();
+
+		for wire in 1..=4 {
+			let queue: VecDeque<(usize, usize)> =
+				source.iter().copied().filter(|&(i, j)| has_wire(&self.tiles[i][j], wire)).collect();
+			if queue.is_empty() {
+				continue;
+			}
+
+			self.wiring.signals.push_back(Signal {
+				wire,
+				queue,
+				visited: source.clone(),
+				source: source.clone(),
+				hit: HashSet::new(),
+			});
+		}
+	}
+
+	// Runs the timers and carries the signals on until the budget runs out, returns the areas that changed
+	pub fn update_wiring(&mut self) -> Vec<(usize, usize, usize, usize)> {
+		if !self.wiring.found_timers {
+			self.wiring.found_timers = true;
+			for (x, column) in self.tiles.iter().enumerate() {
+				for (y, tile) in column.iter().enumerate() {
+					if tile.active && tile.id == TIMERS && tile.frame_y != 0 {
+						self.wiring.timers.insert((x, y), timer_interval(tile));
+					}
+				}
+			}
+		}
+
+		let mut fired = vec![];
+		self.wiring.timers.retain(|&(x, y), left| {
+			let tile = &self.tiles[x][y];
+			// Broken or turned off some other way
+			if !tile.active || tile.id != TIMERS || tile.frame_y == 0 {
+				return false;
+			}
+			*left -= 1;
+			if *left == 0 {
+				*left = timer_interval(tile);
+				fired.push((x, y));
+			}
+			true
+		});
+		for (x, y) in fired {
+			self.trip_wire(x, y, 1, 1);
+		}
+
+		let mut changed = vec![];
+		let mut steps = 0;
+		while steps < MAX_WIRE_STEPS {
+			let Some(mut signal) = self.wiring.signals.pop_front() else {
+				break;
+			};
+			while steps < MAX_WIRE_STEPS {
+				let Some((x, y)) = signal.queue.pop_front() else {
+					break;
+				};
+				steps += 1;
+				self.carry_signal(&mut signal, x, y, &mut changed);
+			}
+			if !signal.queue.is_empty() {
+				self.wiring.signals.push_front(signal);
+			}
+		}
+
+		for ((x, y), faulty) in mem::take(&mut self.wiring.gates) {
+			changed.extend(self.check_gate(x, y, faulty));
+		}
+
+		changed
+	}
+
+	fn carry_signal(&mut self, signal: &mut Signal, x: usize, y: usize, changed: &mut Vec<(usize, usize, usize, usize)>) {
+		if !signal.source.contains(&(x, y)) {
+			if self.tiles[x][y].actuator && self.actuate(x, y) {
+				changed.push((x, y, 1, 1));
+			}
+			if let Some((ox, oy, _, _)) = self.object_at(x, y) {
+				if signal.hit.insert((ox, oy)) {
+					changed.extend(self.hit_wire(x, y));
+				}
+			}
+		}
+
+		for (i, j) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+			if self.in_world(i as i32, j as i32, 1) && has_wire(&self.tiles[i][j], signal.wire) && signal.visited.insert((i, j)) {
+				signal.queue.push_back((i, j));
+			}
+		}
+	}
+
+	// Port of Wiring.DeActive and Wiring.ReActive, actuated blocks can be walked through
+	fn actuate(&mut self, x: usize, y: usize) -> bool {
+		let tile = &self.tiles[x][y];
+		if !tile.active {
+			return false;
+		}
+		if !tile.in_active {
+			let id = tile.id as usize;
+			// Blocks holding up chests, altars and the like stay put
+			if !SOLID[id] || NOT_REALLY_SOLID[id] || tile.id == CLOSED_DOOR || is_chest(tile) || !self.can_kill_tile(x, y) {
+				return false;
+			}
+		}
+
+		let tile = &mut self.tiles[x][y];
+		tile.in_active = !tile.in_active;
+		self.tile_changed(x, y);
+		true
+	}
+
+	// Port of the parts of Wiring.HitWireSingle for doors and lights, returns the area that changed
+	fn hit_wire(&mut self, x: usize, y: usize) -> Option<(usize, usize, usize, usize)> {
+		let (ox, oy, width, height) = self.object_at(x, y)?;
+		let origin = &self.tiles[ox][oy];
+		let (dx, dy) = match origin.id {
+			CLOSED_DOOR => return self.open_door(ox, oy),
+			OPEN_DOOR => return self.close_door(ox, oy),
+			ACTIVE_STONE_BLOCK | INACTIVE_STONE_BLOCK => {
+				let tile = &mut self.tiles[x][y];
+				tile.id = if tile.id == ACTIVE_STONE_BLOCK { INACTIVE_STONE_BLOCK } else { ACTIVE_STONE_BLOCK };
+				self.tile_changed(x, y);
+				return Some((x, y, 1, 1));
+			}
+			LOGIC_GATE_LAMP => {
+				if let Some((i, j, faulty)) = self.toggle_lamp(x, y) {
+					*self.wiring.gates.entry((i, j)).or_default() |= faulty;
+				}
+				return Some((x, y, 1, 1));
+			}
+			TORCHES => (if origin.frame_x < 66 { 66 } else { -66 }, 0),
+			CAMPFIRE => (0, if origin.frame_y < 36 { 36 } else { -36 }),
+			id if is_light(id) => {
+				let step = width as i16 * 18;
+				(if origin.frame_x % (step * 2) < step { step } else { -step }, 0)
+			}
+			_ => return None,
+		};
+
+		let id = origin.id;
+		for i in ox..ox + width {
+			for j in oy..oy + height {
+				let tile = &mut self.tiles[i][j];
+				if tile.active && tile.id == id {
+					tile.frame_x += dx;
+					tile.frame_y += dy;
+				}
+			}
+		}
+		self.area_changed(ox, oy, width, height);
+		Some((ox, oy, width, height))
+	}
+
+	// Port of WorldGen.OpenDoor, the door swings to whichever side is free, right first
+	fn open_door(&mut self, x: usize, y: usize) -> Option<(usize, usize, usize, usize)> {
+		let tile = &self.tiles[x][y];
+		let style = tile.frame_y / 54 + tile.frame_x / 54 * 36;
+		if style == LOCKED_DOOR_STYLE {
+			return None;
+		}
+
+		let right = (y..y + 3).all(|j| !self.tiles[x + 1][j].active);
+		let left = (y..y + 3).all(|j| !self.tiles[x - 1][j].active);
+		let (start, frame_x) = match (right, left) {
+			(true, _) => (x, 0),
+			(_, true) => (x - 1, 36),
+			_ => return None,
+		};
+
+		let door = self.tiles[x][y].clone();
+		for i in 0..2 {
+			for j in 0..3 {
+				let tile = &mut self.tiles[start + i][y + j];
+				let frame_x = frame_x + i as i16 * 18 + style / 36 * 72;
+				set_door_tile(tile, &door, OPEN_DOOR, frame_x, style % 36 * 54 + j as i16 * 18);
+			}
+		}
+		self.area_changed(start, y, 2, 3);
+		Some((start, y, 2, 3))
+	}
+
+	// Port of WorldGen.CloseDoor, the door folds back onto its hinge
+	fn close_door(&mut self, x: usize, y: usize) -> Option<(usize, usize, usize, usize)> {
+		let tile = &self.tiles[x][y];
+		let style = tile.frame_y / 54 + tile.frame_x / 72 * 36;
+		let hinge = if tile.frame_x % 72 < 36 { x } else { x + 1 };
+
+		let door = tile.clone();
+		for i in x..x + 2 {
+			for j in 0..3 {
+				let tile = &mut self.tiles[i][y + j];
+				if i == hinge {
+					set_door_tile(tile, &door, CLOSED_DOOR, style / 36 * 54, style % 36 * 54 + j as i16 * 18);
+				} else {
+					tile.clear_block();
+				}
+			}
+		}
+		self.area_changed(x, y, 2, 3);
+		Some((x, y, 2, 3))
+	}
+
+	// Port of PressurePlateHelper.Update
+	// Weighted plates send a signal when the first player steps on them and when the last one steps off
+	pub fn update_pressure_plates(&mut self, players: &[Hitbox]) -> Vec<(usize, usize, usize, usize)> {
+		let mut pressed = HashSet::new();
+		for &(px, py, width, height) in players {
+			let (left, top) = ((px / 16.).max(0.) as usize, (py / 16.).max(0.) as usize);
+			let (right, bottom) = (((px + width) / 16.).max(0.) as usize, ((py + height) / 16.).max(0.) as usize);
+			for x in left..=right {
+				for y in top..=bottom {
+					if !self.in_world(x as i32, y as i32, 1) {
+						continue;
+					}
+					let tile = &self.tiles[x][y];
+					if tile.active && tile.id == WEIGHTED_PRESSURE_PLATE {
+						pressed.insert((x, y));
+					}
+				}
+			}
+		}
+
+		let mut toggled = vec![];
+		self.weighted_pressure_plates.retain(|plate| {
+			let still = pressed.remove(&(plate.x as usize, plate.y as usize));
+			if !still {
+				toggled.push((plate.x as usize, plate.y as usize));
+			}
+			still
+		});
+		for &(x, y) in &pressed {
+			self.weighted_pressure_plates.push(WeightedPressurePlate { x: x as i32, y: y as i32 });
+		}
+		toggled.extend(pressed);
+
+		toggled.into_iter().filter_map(|(x, y)| self.hit_switch(x, y)).collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::world::binary::FileReader;
+
+	#[test]
+	fn doors_leave_nothing_stale_behind() {
+		let fixture = include_bytes!("../../tests/fixtures/small.wld");
+		let mut world = World::read_world_v2(&mut FileReader::new(fixture.to_vec())).unwrap();
+		for x in 9..13 {
+			for y in 10..13 {
+				let tile = &mut world.tiles[x][y];
+				tile.clear_block();
+				// What a removed sloped and actuated block leaves behind
+				tile.slope = 2;
+				tile.in_active = true;
+				tile.color = 5;
+			}
+			world.tiles[x][9] = Tile {
+				active: true,
+				..Default::default()
+			};
+			world.tiles[x][13] = world.tiles[x][9].clone();
+		}
+		for j in 0..3 {
+			let tile = &mut world.tiles[10][10 + j];
+			tile.clear_block();
+			tile.active = true;
+			tile.id = CLOSED_DOOR;
+			tile.frame_y = j as i16 * 18;
+			tile.color = 3;
+		}
+
+		assert_eq!(world.open_door(10, 10), Some((10, 10, 2, 3)));
+		for tile in world.tiles[10][10..13].iter().chain(&world.tiles[11][10..13]) {
+			assert_eq!((tile.active, tile.id, tile.slope, tile.in_active, tile.color), (true, OPEN_DOOR, 0, false, 3));
+		}
+
+		assert_eq!(world.close_door(10, 10), Some((10, 10, 2, 3)));
+		for tile in &world.tiles[10][10..13] {
+			assert_eq!((tile.id, tile.slope, tile.in_active, tile.color), (CLOSED_DOOR, 0, false, 3));
+		}
+		assert!(world.tiles[11][10..13].iter().all(|tile| !tile.active && tile.color == 0));
+	}
+}
@@ -0,0 +1,544 @@
+use crate::binary::writer::Writer;
+use crate::world::entity::*;
+use crate::world::reader::EPOCH_DIFFERENCE;
+use crate::world::tile::*;
+use crate::world::types::*;
+use std::io::{self, Cursor, Seek, SeekFrom, Write};
+use std::time::UNIX_EPOCH;
+use std::{fs, path::{Path, PathBuf}};
+
+use super::transpiled::tile_flags::{ALLOWS_SAVE_COMPRESSION_BATCHING, FRAME};
+
+const POSITION_COUNT: i16 = 11;
+
+impl World {
+	pub fn to_file(&self, path: &Path) -> io::Result<()> {
+		let mut writer = Writer::new(Cursor::new(vec![]));
+		self.to_writer(&mut writer)?;
+		fs::write(path, writer.into_inner().into_inner())
+	}
+
+	// Writes to a temporary file first so a crash mid-save never leaves a truncated world behind
+	// The previous saves are kept as path.bak.1 (newest) up to path.bak.<backups> (oldest)
+	pub fn save(&self, path: &Path, backups: usize) -> io::Result<()> {
+		let tmp = with_suffix(path, ".tmp");
+		self.to_file(&tmp)?;
+
+		if backups > 0 && path.exists() {
+			for i in (1..backups).rev() {
+				let from = with_suffix(path, &format!(".bak.{}", i));
+				if from.exists() {
+					fs::rename(&from, with_suffix(path, &format!(".bak.{}", i + 1)))?;
+				}
+			}
+			fs::copy(path, with_suffix(path, ".bak.1"))?;
+		}
+
+		fs::rename(&tmp, path)
+	}
+
+	pub fn to_writer<T: Write + Seek>(&self, w: &mut Writer<T>) -> io::Result<()> {
+		Self::write_metadata(w, &self.metadata)?;
+
+		// Every section is preceded by the position table, which can only be filled in once we know where each section ends
+		let table = w.dst.stream_position()?;
+		let mut positions = Vec::with_capacity(POSITION_COUNT as usize);
+		Self::write_format(w)?;
+		positions.push(w.dst.stream_position()?);
+		Self::write_header(w, &self.header)?;
+		positions.push(w.dst.stream_position()?);
+		Self::write_tiles(w, &self.tiles)?;
+		positions.push(w.dst.stream_position()?);
+		Self::write_chests(w, &self.chests)?;
+		positions.push(w.dst.stream_position()?);
+		Self::write_signs(w, &self.signs)?;
+		positions.push(w.dst.stream_position()?);
+		Self::write_shimmered_npcs(w, &self.shimmered_npcs, &self.npcs)?;
+		Self::write_npcs(w, &self.npcs)?;
+		Self::write_persistent_npcs(w, &self.persistent_npcs)?;
+		positions.push(w.dst.stream_position()?);
+		Self::write_entities(w, &self.entities)?;
+		positions.push(w.dst.stream_position()?);
+		Self::write_weighted_pressure_plates(w, &self.weighted_pressure_plates)?;
+		positions.push(w.dst.stream_position()?);
+		Self::write_room_locations(w, &self.room_locations)?;
+		positions.push(w.dst.stream_position()?);
+		Self::write_bestiary(w, &self.bestiary)?;
+		positions.push(w.dst.stream_position()?);
+		Self::write_creative_powers(w, &self.creative_powers)?;
+		positions.push(w.dst.stream_position()?);
+		Self::write_footer(w, &self.header)?;
+
+		let end = w.dst.stream_position()?;
+		w.dst.seek(SeekFrom::Start(table))?;
+		w.write_i16(POSITION_COUNT)?;
+		for p in positions {
+			w.write_i32(p as i32)?;
+		}
+		w.dst.seek(SeekFrom::Start(end))?;
+
+		Ok(())
+	}
+
+	fn write_metadata<T: Write>(w: &mut Writer<T>, metadata: &Metadata) -> io::Result<()> {
+		w.write_i32(WORLD_VERSION)?;
+		w.write_all(MAGIC_STRING)?;
+		w.write_byte(metadata.file_type.clone() as u8)?;
+		w.write_u32(metadata.revision)?;
+		w.write_u64(metadata.favorite as u64)
+	}
+
+	// The tile importance table is always written from the current tile flags since that's what Tile::encode uses
+	fn write_format<T: Write>(w: &mut Writer<T>) -> io::Result<()> {
+		w.write_i16(POSITION_COUNT)?;
+		for _ in 0..POSITION_COUNT {
+			w.write_i32(0)?;
+		}
+
+		w.write_u16(FRAME.len() as u16)?;
+		let mut byte = 0;
+		let mut mask = 1;
+		for &important in FRAME {
+			if important {
+				byte |= mask;
+			}
+
+			if mask == 128 {
+				w.write_byte(byte)?;
+				byte = 0;
+				mask = 1;
+			} else {
+				mask <<= 1;
+			}
+		}
+
+		if mask != 1 {
+			w.write_byte(byte)?;
+		}
+
+		Ok(())
+	}
+
+	fn write_header<T: Write>(w: &mut Writer<T>, h: &Header) -> io::Result<()> {
+		w.write_string(&h.name)?;
+		w.write_string(&h.seed_text)?;
+		w.write_u64(h.worldgen_version)?;
+		w.write_all(&h.uuid.unwrap_or_default())?;
+		w.write_i32(h.id)?;
+		w.write_i32(h.left)?;
+		w.write_i32(h.right)?;
+		w.write_i32(h.top)?;
+		w.write_i32(h.bottom)?;
+		w.write_i32(h.height)?;
+		w.write_i32(h.width)?;
+		w.write_i32(h.game_mode.clone() as i32)?;
+		w.write_bool(h.world_drunk)?;
+		w.write_bool(h.world_for_the_worthy)?;
+		w.write_bool(h.world_anniversary)?;
+		w.write_bool(h.world_dont_starve)?;
+		w.write_bool(h.world_not_the_bees)?;
+		w.write_bool(h.world_remix)?;
+		w.write_bool(h.world_no_traps)?;
+		w.write_bool(h.world_zenith)?;
+
+		let ticks = h
+			.creation_time
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_nanos() as u64 / 100
+			+ EPOCH_DIFFERENCE * 10_000;
+		w.write_u64(ticks | (h.creation_time_kind as u64) << 62)?;
+
+		w.write_byte(h.moon_type as u8)?;
+		for x in h.tree_x {
+			w.write_i32(x)?;
+		}
+		for style in h.tree_style {
+			w.write_i32(style)?;
+		}
+		for x in h.cave_back_x {
+			w.write_i32(x)?;
+		}
+		for style in h.cave_back_style {
+			w.write_i32(style)?;
+		}
+		w.write_i32(h.ice_back_style)?;
+		w.write_i32(h.jungle_back_style)?;
+		w.write_i32(h.hell_back_style)?;
+		w.write_i32(h.spawn_x)?;
+		w.write_i32(h.spawn_y)?;
+		w.write_f64(h.world_surface)?;
+		w.write_f64(h.rock_layer)?;
+		w.write_f64(h.time)?;
+		w.write_bool(h.day_time)?;
+		w.write_i32(h.moon_phase)?;
+		w.write_bool(h.blood_moon)?;
+		w.write_bool(h.eclipse)?;
+		w.write_i32(h.dungeon_x)?;
+		w.write_i32(h.dungeon_y)?;
+		w.write_bool(h.has_crimson)?;
+		w.write_bool(h.downed_boss_1)?;
+		w.write_bool(h.downed_boss_2)?;
+		w.write_bool(h.downed_boss_3)?;
+		w.write_bool(h.downed_queen_bee)?;
+		w.write_bool(h.downed_mech_boss_1)?;
+		w.write_bool(h.downed_mech_boss_2)?;
+		w.write_bool(h.downed_mech_boss_3)?;
+		w.write_bool(h.downed_mech_boss_any)?;
+		w.write_bool(h.downed_plant_boss)?;
+		w.write_bool(h.downed_golem_boss)?;
+		w.write_bool(h.downed_slime_king)?;
+		w.write_bool(h.saved_goblin)?;
+		w.write_bool(h.saved_wizard)?;
+		w.write_bool(h.saved_mechanic)?;
+		w.write_bool(h.downed_goblins)?;
+		w.write_bool(h.downed_clown)?;
+		w.write_bool(h.downed_frost)?;
+		w.write_bool(h.downed_pirates)?;
+		w.write_bool(h.smashed_shadow_orb)?;
+		w.write_bool(h.spawn_meteor)?;
+		w.write_byte(h.shadow_orb_count as u8)?;
+		w.write_i32(h.altar_count)?;
+		w.write_bool(h.hard_mode)?;
+		w.write_bool(h.after_party_of_doom)?;
+		w.write_i32(h.invasion_delay)?;
+		w.write_i32(h.invasion_size)?;
+		w.write_i32(h.invasion_type)?;
+		w.write_f64(h.invasion_x)?;
+		w.write_f64(h.slime_rain_time)?;
+		w.write_byte(h.sundial_cooldown as u8)?;
+		w.write_bool(h.raining)?;
+		w.write_i32(h.rain_time)?;
+		w.write_f32(h.max_rain)?;
+		w.write_i32(h.ore_tier_cobalt)?;
+		w.write_i32(h.ore_tier_mythril)?;
+		w.write_i32(h.ore_tier_adamantite)?;
+		w.write_all(&h.bg[..8])?;
+		w.write_i32(h.cloud_bg_active as i32)?;
+		w.write_i16(h.num_clouds)?;
+		w.write_f32(h.wind_speed_target)?;
+
+		w.write_i32(h.angler_who_finished_today.len() as i32)?;
+		for name in &h.angler_who_finished_today {
+			w.write_string(name)?;
+		}
+
+		w.write_bool(h.saved_angler)?;
+		w.write_i32(h.angler_quest)?;
+		w.write_bool(h.saved_stylist)?;
+		w.write_bool(h.saved_tax_collector)?;
+		w.write_bool(h.saved_golfer)?;
+		w.write_i32(h.invasion_size_start)?;
+		w.write_i32(h.cultist_delay)?;
+
+		w.write_i16(h.npc_kill_counts.len() as i16)?;
+		for &kc in &h.npc_kill_counts {
+			w.write_i32(kc)?;
+		}
+
+		w.write_bool(h.fast_forward_time_to_dawn)?;
+		w.write_bool(h.downed_fishron)?;
+		w.write_bool(h.downed_martians)?;
+		w.write_bool(h.downed_ancient_cultist)?;
+		w.write_bool(h.downed_moonlord)?;
+		w.write_bool(h.downed_halloween_king)?;
+		w.write_bool(h.downed_halloween_tree)?;
+		w.write_bool(h.downed_christmas_ice_queen)?;
+		w.write_bool(h.downed_christmas_santank)?;
+		w.write_bool(h.downed_christmas_tree)?;
+		w.write_bool(h.downed_tower_solar)?;
+		w.write_bool(h.downed_tower_vortex)?;
+		w.write_bool(h.downed_tower_nebula)?;
+		w.write_bool(h.downed_tower_stardust)?;
+		w.write_bool(h.tower_active_solar)?;
+		w.write_bool(h.tower_active_vortex)?;
+		w.write_bool(h.tower_active_nebula)?;
+		w.write_bool(h.tower_active_stardust)?;
+		w.write_bool(h.lunar_apocalypse_is_up)?;
+
+		w.write_bool(h.party_manual)?;
+		w.write_bool(h.party_genuine)?;
+		w.write_i32(h.party_cooldown)?;
+		w.write_i32(h.party_celebrating_npcs.len() as i32)?;
+		for &npc in &h.party_celebrating_npcs {
+			w.write_i32(npc)?;
+		}
+
+		w.write_bool(h.sandstorm_happening)?;
+		w.write_i32(h.sandstorm_time_left)?;
+		w.write_f32(h.sandstorm_severity)?;
+		w.write_f32(h.sandstorm_intended_severity)?;
+
+		w.write_bool(h.saved_bartender)?;
+		w.write_bool(h.downed_dd2_invasion_t1)?;
+		w.write_bool(h.downed_dd2_invasion_t2)?;
+		w.write_bool(h.downed_dd2_invasion_t3)?;
+
+		w.write_byte(h.bg[8])?;
+		w.write_byte(h.bg[9])?;
+		w.write_all(&h.bg[10..])?;
+
+		w.write_bool(h.combat_book_was_used)?;
+
+		w.write_i32(h.lantern_night_cooldown)?;
+		w.write_bool(h.lantern_night_genuine)?;
+		w.write_bool(h.lantern_night_manual)?;
+		w.write_bool(h.lantern_night_next_night_is_genuine)?;
+
+		w.write_i32(h.tree_top_variations.len() as i32)?;
+		for &variation in &h.tree_top_variations {
+			w.write_i32(variation)?;
+		}
+
+		w.write_bool(h.force_halloween_for_today)?;
+		w.write_bool(h.force_xmas_for_today)?;
+
+		w.write_i32(h.ore_tier_copper)?;
+		w.write_i32(h.ore_tier_iron)?;
+		w.write_i32(h.ore_tier_silver)?;
+		w.write_i32(h.ore_tier_gold)?;
+
+		w.write_bool(h.bought_cat)?;
+		w.write_bool(h.bought_dog)?;
+		w.write_bool(h.bought_bunny)?;
+
+		w.write_bool(h.downed_empress_of_light)?;
+		w.write_bool(h.downed_queen_slime)?;
+
+		w.write_bool(h.downed_deerclops)?;
+
+		w.write_bool(h.unlocked_slime_blue_spawn)?;
+
+		w.write_bool(h.unlocked_merchant_spawn)?;
+		w.write_bool(h.unlocked_demolition_spawn)?;
+		w.write_bool(h.unlocked_party_girl_spawn)?;
+		w.write_bool(h.unlocked_dye_trader_spawn)?;
+		w.write_bool(h.unlocked_truffle_spawn)?;
+		w.write_bool(h.unlocked_arms_dealer_spawn)?;
+		w.write_bool(h.unlocked_nurse_spawn)?;
+		w.write_bool(h.unlocked_princess_spawn)?;
+
+		w.write_bool(h.combat_book_volume_two_was_used)?;
+
+		w.write_bool(h.peddlers_satchel_was_used)?;
+
+		w.write_bool(h.unlocked_slime_green_spawn)?;
+		w.write_bool(h.unlocked_slime_old_spawn)?;
+		w.write_bool(h.unlocked_slime_purple_spawn)?;
+		w.write_bool(h.unlocked_slime_rainbow_spawn)?;
+		w.write_bool(h.unlocked_slime_red_spawn)?;
+		w.write_bool(h.unlocked_slime_yellow_spawn)?;
+		w.write_bool(h.unlocked_slime_copper_spawn)?;
+
+		w.write_bool(h.fast_forward_time_to_dusk)?;
+		w.write_byte(h.moondial_cooldown)
+	}
+
+	fn write_tiles<T: Write>(w: &mut Writer<T>, tiles: &[Vec<Tile>]) -> io::Result<()> {
+		for column in tiles {
+			let mut y = 0;
+			while y < column.len() {
+				let tile = &column[y];
+				let mut repeat = 0;
+				if !tile.active || ALLOWS_SAVE_COMPRESSION_BATCHING[tile.id as usize] {
+					while y + repeat + 1 < column.len() && column[y + repeat + 1] == *tile {
+						repeat += 1;
+					}
+				}
+
+				tile.encode(w, repeat)?;
+				y += repeat + 1;
+			}
+		}
+
+		Ok(())
+	}
+
+	fn write_chests<T: Write>(w: &mut Writer<T>, chests: &[Option<Chest>]) -> io::Result<()> {
+		w.write_i16(chests.iter().flatten().count() as i16)?;
+		w.write_i16(CHEST_SIZE)?;
+
+		let empty = ChestItem::default();
+		for chest in chests.iter().flatten() {
+			w.write_i32(chest.x)?;
+			w.write_i32(chest.y)?;
+			w.write_string(&chest.name)?;
+			for i in 0..CHEST_SIZE as usize {
+				let item = chest.items.get(i).unwrap_or(&empty);
+				w.write_i16(item.stack)?;
+				if item.stack != 0 {
+					w.write_i32(item.id)?;
+					w.write_byte(item.prefix)?;
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	fn write_signs<T: Write>(w: &mut Writer<T>, signs: &[Option<Sign>]) -> io::Result<()> {
+		w.write_i16(signs.iter().flatten().count() as i16)?;
+		for sign in signs.iter().flatten() {
+			w.write_string(&sign.text)?;
+			w.write_i32(sign.x)?;
+			w.write_i32(sign.y)?;
+		}
+
+		Ok(())
+	}
+
+	fn write_shimmered_npcs<T: Write>(
+		w: &mut Writer<T>,
+		shimmers: &[i32],
+		npcs: &[NPC],
+	) -> io::Result<()> {
+		// NPCs may have been shimmered since the world was loaded
+		let mut ids = shimmers.to_vec();
+		ids.extend(npcs.iter().filter(|npc| npc.shimmer).map(|npc| npc.id));
+		ids.sort_unstable();
+		ids.dedup();
+
+		w.write_i32(ids.len() as i32)?;
+		for id in ids {
+			w.write_i32(id)?;
+		}
+
+		Ok(())
+	}
+
+	fn write_npcs<T: Write>(w: &mut Writer<T>, npcs: &[NPC]) -> io::Result<()> {
+		for npc in npcs {
+			w.write_bool(true)?;
+			w.write_i32(npc.id)?;
+			w.write_string(&npc.name)?;
+			w.write_vector2(npc.position.clone())?;
+			w.write_bool(npc.homeless)?;
+			w.write_i32(npc.home_x)?;
+			w.write_i32(npc.home_y)?;
+			w.write_byte((npc.variation != 0) as u8)?;
+			if npc.variation != 0 {
+				w.write_i32(npc.variation)?;
+			}
+		}
+
+		w.write_bool(false)
+	}
+
+	fn write_persistent_npcs<T: Write>(
+		w: &mut Writer<T>,
+		npcs: &[PersistentNPC],
+	) -> io::Result<()> {
+		for npc in npcs {
+			w.write_bool(true)?;
+			w.write_i32(npc.id)?;
+			w.write_vector2(npc.position.clone())?;
+		}
+
+		w.write_bool(false)
+	}
+
+	fn write_entities<T: Write>(w: &mut Writer<T>, entities: &[Entity]) -> io::Result<()> {
+		w.write_i32(entities.len() as i32)?;
+		for entity in entities {
+			entity.write(w)?;
+		}
+
+		Ok(())
+	}
+
+	fn write_weighted_pressure_plates<T: Write>(
+		w: &mut Writer<T>,
+		wpp: &[WeightedPressurePlate],
+	) -> io::Result<()> {
+		w.write_i32(wpp.len() as i32)?;
+		for plate in wpp {
+			w.write_i32(plate.x)?;
+			w.write_i32(plate.y)?;
+		}
+
+		Ok(())
+	}
+
+	fn write_room_locations<T: Write>(
+		w: &mut Writer<T>,
+		rl: &[RoomLocation],
+	) -> io::Result<()> {
+		w.write_i32(rl.len() as i32)?;
+		for room in rl {
+			w.write_i32(room.id)?;
+			w.write_i32(room.x)?;
+			w.write_i32(room.y)?;
+		}
+
+		Ok(())
+	}
+
+	fn write_bestiary<T: Write>(w: &mut Writer<T>, bestiary: &Bestiary) -> io::Result<()> {
+		w.write_i32(bestiary.kills.len() as i32)?;
+		for (npc, count) in &bestiary.kills {
+			w.write_string(npc)?;
+			w.write_i32(*count)?;
+		}
+
+		w.write_i32(bestiary.sights.len() as i32)?;
+		for npc in &bestiary.sights {
+			w.write_string(npc)?;
+		}
+
+		w.write_i32(bestiary.chats.len() as i32)?;
+		for npc in &bestiary.chats {
+			w.write_string(npc)?;
+		}
+
+		Ok(())
+	}
+
+	fn write_creative_powers<T: Write>(
+		w: &mut Writer<T>,
+		powers: &[CreativePower],
+	) -> io::Result<()> {
+		for power in powers {
+			w.write_bool(true)?;
+			power.encode_file(w)?;
+		}
+
+		w.write_bool(false)
+	}
+
+	fn write_footer<T: Write>(w: &mut Writer<T>, header: &Header) -> io::Result<()> {
+		w.write_bool(true)?;
+		w.write_string(&header.name)?;
+		w.write_i32(header.id)
+	}
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+	let mut name = path.as_os_str().to_owned();
+	name.push(suffix);
+	PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::world::binary::FileReader;
+
+	// A small generated world with chests, signs, town and persistent NPCs and every kind of tile entity
+	const FIXTURE: &[u8] = include_bytes!("../../tests/fixtures/small.wld");
+
+	#[test]
+	fn round_trip() {
+		let world = World::read_world_v2(&mut FileReader::new(FIXTURE.to_vec())).unwrap();
+		assert_eq!(world.chests.iter().flatten().count(), 2);
+		assert!(world.signs.iter().flatten().count() > 0);
+		assert_eq!(world.npcs.len(), 3);
+		assert_eq!(world.persistent_npcs.len(), 1);
+		assert_eq!(world.entities.len(), 6);
+
+		let mut w = Writer::new(Cursor::new(vec![]));
+		world.to_writer(&mut w).unwrap();
+		let bytes = w.into_inner().into_inner();
+		let difference = bytes.iter().zip(FIXTURE).position(|(a, b)| a != b);
+		assert!(bytes == FIXTURE, "written world differs from byte {:?}", difference);
+	}
+}